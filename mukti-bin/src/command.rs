@@ -1,17 +1,23 @@
 // Copyright (c) The mukti Contributors
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
-use crate::{
-    checksums::{backfill_checksums, fetch_release_checksums},
-    errors::NameValueParseError,
-    redirects::{generate_redirects, RedirectFlavor},
-    release_json::{read_release_json, update_release_json, write_releases_json},
-};
 use camino::Utf8PathBuf;
-use clap::{Parser, Subcommand};
-use color_eyre::Result;
+use clap::{Parser, Subcommand, ValueEnum};
+use color_eyre::{
+    eyre::{bail, WrapErr},
+    Result,
+};
+use mukti_core::{
+    abort_rollout, add_release, advance_rollout, annotate_github_release, backfill_checksums,
+    clear_rollout, fetch_release_checksums, generate_appcast, generate_appinstallers,
+    generate_asdf, generate_chocolatey, generate_redirects, generate_targets_json,
+    generate_versions, read_release_json, start_rollout, verify_artifacts, write_releases_json,
+    Alias, AnnotateOptions, AppInstallerOptions, AppcastOptions, Archive, ChecksumProgress,
+    ChocolateyOptions, EventLog, MuktiConfig, RedirectFlavor, RedirectOptions, TargetFormat,
+    VerifyOptions,
+};
+use mukti_metadata::ReleaseEventKind;
 use semver::Version;
-use std::str::FromStr;
 
 #[doc(hidden)]
 #[derive(Debug, Parser)]
@@ -23,6 +29,18 @@ pub struct MuktiApp {
     /// JSON file to edit
     #[clap(long, global = true, default_value = ".releases.json")]
     json: Utf8PathBuf,
+
+    /// Configuration file (optional)
+    #[clap(long, global = true, default_value = "mukti.toml")]
+    config: Utf8PathBuf,
+
+    /// Append-only log of changes made to the JSON file, as newline-delimited JSON
+    #[clap(long, global = true, value_name = "PATH")]
+    event_log: Option<Utf8PathBuf>,
+
+    /// Who is making changes, for the event log [default: $GITHUB_ACTOR or $USER]
+    #[clap(long, global = true)]
+    actor: Option<String>,
 }
 
 #[derive(Debug, Subcommand)]
@@ -57,15 +75,193 @@ enum MuktiCommand {
 
         /// The flavor of redirects to generate.
         #[clap(long, short, value_enum)]
-        flavor: RedirectFlavor,
+        flavor: FlavorArg,
 
         /// Prefix for URLs.
         #[clap(long, default_value = "/")]
         prefix: String,
 
+        /// Rewrite /versions to the version list written by generate-versions.
+        #[clap(long)]
+        versions: bool,
+
+        /// Directory the platform reads functions from, required for edge flavors.
+        ///
+        /// For Netlify, this is netlify/edge-functions under the base directory. For Cloudflare
+        /// Pages, this is functions under the project root. It must not be inside the output
+        /// directory, which is published as static files.
+        #[clap(long, value_name = "DIR")]
+        functions_dir: Option<Utf8PathBuf>,
+
+        /// Output directory.
+        out_dir: Utf8PathBuf,
+    },
+    /// Generate MSIX App Installer files for the latest release
+    GenerateAppinstaller {
+        /// Base URL that redirects are served from (e.g. https://get.nexte.st).
+        #[clap(long, required = true)]
+        base_url: String,
+
+        /// Package name, matching the Identity element in the MSIX manifest.
+        #[clap(long, required = true)]
+        package_name: String,
+
+        /// Package publisher, matching the Identity element in the MSIX manifest.
+        #[clap(long, required = true)]
+        publisher: String,
+
+        /// Format of MSIX artifacts in the release JSON.
+        #[clap(long, default_value = "msix")]
+        format: String,
+
+        /// Hours between update checks when the app is launched.
+        #[clap(long, default_value = "24")]
+        hours_between_update_checks: u8,
+
+        /// Prompt the user before updating.
+        #[clap(long)]
+        show_prompt: bool,
+
+        /// Block the app from launching until the update is installed.
+        #[clap(long)]
+        update_blocks_activation: bool,
+
+        /// Output directory.
+        out_dir: Utf8PathBuf,
+    },
+    /// Generate a Sparkle appcast.xml for macOS apps
+    GenerateAppcast {
+        /// Base URL that redirects are served from (e.g. https://get.nexte.st).
+        #[clap(long, required = true)]
+        base_url: String,
+
+        /// Title of the appcast channel.
+        #[clap(long, required = true)]
+        title: String,
+
+        /// Target and format of the app archive.
+        #[clap(long, required = true, value_name = "TARGET:FORMAT")]
+        archive: TargetFormat,
+
+        /// Sparkle channel to publish pre-release versions to.
+        #[clap(long, default_value = "beta")]
+        prerelease_channel: String,
+
+        /// Output directory.
+        out_dir: Utf8PathBuf,
+    },
+    /// Generate Chocolatey package sources for a release
+    GenerateChocolatey {
+        /// Base URL that redirects are served from (e.g. https://get.nexte.st).
+        #[clap(long, required = true)]
+        base_url: String,
+
+        /// Chocolatey package ID.
+        #[clap(long, required = true)]
+        package_id: String,
+
+        /// Package authors.
+        #[clap(long, required = true)]
+        authors: String,
+
+        /// Package description.
+        #[clap(long, required = true)]
+        description: String,
+
+        /// Project URL.
+        #[clap(long)]
+        project_url: Option<String>,
+
+        /// Target and format of the Windows artifact (x86_64 or i686, zip or msi).
+        #[clap(long, required = true, value_name = "TARGET:FORMAT")]
+        archive: TargetFormat,
+
+        /// Version to package (default: latest).
+        #[clap(long = "version")]
+        version: Option<Version>,
+
+        /// Output directory.
+        out_dir: Utf8PathBuf,
+    },
+    /// Generate version and download URL listings for asdf and mise plugins
+    GenerateAsdf {
+        /// Base URL that redirects are served from (e.g. https://get.nexte.st).
+        #[clap(long, required = true)]
+        base_url: String,
+
+        /// Output directory.
+        out_dir: Utf8PathBuf,
+    },
+    /// Generate a targets.json file listing the artifacts for each version
+    GenerateTargetsJson {
+        /// Base URL that redirects are served from (e.g. https://get.nexte.st).
+        #[clap(long, required = true)]
+        base_url: String,
+
+        /// Output directory.
+        out_dir: Utf8PathBuf,
+    },
+    /// Generate plain-text lists of versions
+    GenerateVersions {
         /// Output directory.
         out_dir: Utf8PathBuf,
     },
+    /// Add or update a table of download links in a GitHub release description
+    ///
+    /// Requires GITHUB_TOKEN to be set, except with --dry-run for public repositories.
+    AnnotateGithubRelease {
+        /// Release tag to annotate.
+        #[clap(long, required = true)]
+        tag: String,
+
+        /// GitHub repository, in the format OWNER/NAME.
+        #[clap(long, required = true)]
+        repo: String,
+
+        /// Base URL that redirects are served from (e.g. https://get.nexte.st).
+        #[clap(long, required = true)]
+        base_url: String,
+
+        /// Version the tag corresponds to (default: parsed from the tag).
+        #[clap(long = "version")]
+        version: Option<Version>,
+
+        /// GitHub API URL.
+        #[clap(long, default_value = "https://api.github.com")]
+        api_url: String,
+
+        /// Print the new release description rather than updating the release.
+        #[clap(long)]
+        dry_run: bool,
+    },
+    /// Download every artifact of a release through its public URL and verify it
+    ///
+    /// Checks redirects and checksums, along with sizes and EdDSA signatures recorded in sparkle
+    /// metadata. Signatures are only checked if --public-key is passed in.
+    VerifyArtifacts {
+        /// Base URL that redirects are served from (e.g. https://get.nexte.st).
+        #[clap(long, required = true)]
+        base_url: String,
+
+        /// Version to verify (default: latest).
+        #[clap(long = "version")]
+        version: Option<Version>,
+
+        /// Number of release files to download in parallel.
+        #[clap(long, short, default_value = "8")]
+        jobs: usize,
+
+        /// Base64-encoded EdDSA public key to check signatures against (Sparkle's SUPublicEDKey).
+        #[clap(long)]
+        public_key: Option<String>,
+    },
+    /// Manage staged rollouts of the latest version
+    Rollout {
+        #[clap(subcommand)]
+        command: RolloutCommand,
+    },
+    /// Check that the release JSON is internally consistent
+    Validate,
     /// Add checksums to the release JSON
     BackfillChecksums {
         /// Number of release files to download in parallel.
@@ -76,6 +272,14 @@ enum MuktiCommand {
 
 impl MuktiApp {
     pub async fn exec(self) -> Result<()> {
+        // Fall back to the environment so that CI runs are attributed without extra flags.
+        let actor = self.actor.or_else(|| {
+            ["GITHUB_ACTOR", "USER", "USERNAME"]
+                .into_iter()
+                .find_map(|var| std::env::var(var).ok())
+        });
+        let event_log = EventLog::new(self.event_log, actor);
+
         match self.command {
             MuktiCommand::AddRelease {
                 release_url,
@@ -86,29 +290,277 @@ impl MuktiApp {
             } => {
                 let mut release_json = read_release_json(&self.json, true)?;
 
-                let archives = fetch_release_checksums(&archive_prefix, archives, jobs).await;
+                let archives =
+                    fetch_release_checksums(&archive_prefix, archives, jobs, print_progress).await;
+                for archive in &archives {
+                    if let Err(e) = &archive.checksums {
+                        eprintln!(
+                            "failed to compute checksums for {}: {:#}",
+                            archive.archive.name, e
+                        );
+                    }
+                }
 
-                update_release_json(
-                    &mut release_json,
-                    &release_url,
-                    &version,
-                    archives,
-                    &self.json,
-                )?;
+                if let Some(event) =
+                    add_release(&mut release_json, &release_url, &version, archives)?
+                {
+                    write_releases_json(&release_json, &self.json)?;
+                    event_log.record(&release_json, event)?;
+                }
             }
             MuktiCommand::GenerateRedirects {
                 aliases,
                 flavor,
                 prefix,
+                versions,
+                functions_dir,
+                out_dir,
+            } => {
+                let release_json = read_release_json(&self.json, false)?;
+                let config = MuktiConfig::from_path(&self.config)?;
+                let mut options = RedirectOptions::new(flavor.into());
+                options.aliases = &aliases;
+                options.prefix = &prefix;
+                options.versions = versions;
+                options.functions_dir = functions_dir.as_deref();
+                let generated = generate_redirects(&release_json, &config, &options, &out_dir)?;
+                for (wildcard, count) in &generated.wildcards {
+                    eprintln!("found wildcard (matches {count} redirects): {wildcard}");
+                }
+                for warning in &generated.warnings {
+                    eprintln!("warning: {warning}");
+                }
+                if let Some(path) = &generated.edge_function {
+                    eprintln!("wrote staged rollout edge function to {path}");
+                }
+            }
+            MuktiCommand::GenerateAppinstaller {
+                base_url,
+                package_name,
+                publisher,
+                format,
+                hours_between_update_checks,
+                show_prompt,
+                update_blocks_activation,
                 out_dir,
             } => {
                 let release_json = read_release_json(&self.json, false)?;
-                generate_redirects(&release_json, &aliases, flavor, &prefix, &out_dir)?;
+                let mut options = AppInstallerOptions::new(&base_url, &package_name, &publisher);
+                options.format = &format;
+                options.hours_between_update_checks = hours_between_update_checks;
+                options.show_prompt = show_prompt;
+                options.update_blocks_activation = update_blocks_activation;
+                let generated = generate_appinstallers(&release_json, &options, &out_dir)?;
+                eprintln!(
+                    "wrote {} App Installer files for version {}",
+                    generated.file_count, generated.version
+                );
+            }
+            MuktiCommand::GenerateAppcast {
+                base_url,
+                title,
+                archive,
+                prerelease_channel,
+                out_dir,
+            } => {
+                let release_json = read_release_json(&self.json, false)?;
+                let mut options = AppcastOptions::new(&base_url, &title, &archive);
+                options.prerelease_channel = &prerelease_channel;
+                let generated = generate_appcast(&release_json, &options, &out_dir)?;
+                for version in &generated.unsigned_versions {
+                    eprintln!(
+                        "warning: version {version} has no EdDSA signature for {}.{}",
+                        archive.target, archive.format
+                    );
+                }
+                eprintln!("wrote appcast with {} items", generated.item_count);
+            }
+            MuktiCommand::GenerateChocolatey {
+                base_url,
+                package_id,
+                authors,
+                description,
+                project_url,
+                archive,
+                version,
+                out_dir,
+            } => {
+                let release_json = read_release_json(&self.json, false)?;
+                let mut options = ChocolateyOptions::new(
+                    &base_url,
+                    &package_id,
+                    &authors,
+                    &description,
+                    &archive,
+                );
+                options.project_url = project_url.as_deref();
+                options.version = version.as_ref();
+                let version = generate_chocolatey(&release_json, &options, &out_dir)?;
+                eprintln!("wrote Chocolatey package for version {version}");
+            }
+            MuktiCommand::GenerateAsdf { base_url, out_dir } => {
+                let release_json = read_release_json(&self.json, false)?;
+                let count = generate_asdf(&release_json, &base_url, &out_dir)?;
+                eprintln!("wrote asdf listings for {count} versions");
+            }
+            MuktiCommand::GenerateTargetsJson { base_url, out_dir } => {
+                let release_json = read_release_json(&self.json, false)?;
+                let count = generate_targets_json(&release_json, &base_url, &out_dir)?;
+                eprintln!("wrote targets.json for {count} versions");
+            }
+            MuktiCommand::GenerateVersions { out_dir } => {
+                let release_json = read_release_json(&self.json, false)?;
+                generate_versions(&release_json, &out_dir)?;
+            }
+            MuktiCommand::AnnotateGithubRelease {
+                tag,
+                repo,
+                base_url,
+                version,
+                api_url,
+                dry_run,
+            } => {
+                let release_json = read_release_json(&self.json, false)?;
+                let mut options = AnnotateOptions::new(&repo, &tag, &base_url);
+                options.api_url = &api_url;
+                options.version = version.as_ref();
+                options.dry_run = dry_run;
+                options.user_agent = concat!("mukti-bin/", env!("CARGO_PKG_VERSION"));
+                // A dry run only reads the release, which doesn't need a token for public
+                // repositories.
+                let token = match std::env::var("GITHUB_TOKEN") {
+                    Ok(token) => Some(token),
+                    Err(_) if dry_run => None,
+                    Err(err) => {
+                        return Err(err).wrap_err(
+                            "GITHUB_TOKEN must be set to a token with write access to releases",
+                        )
+                    }
+                };
+                match annotate_github_release(&release_json, &options, token.as_deref()).await? {
+                    None => eprintln!("release {tag} is already up to date"),
+                    Some(new_body) if dry_run => println!("{new_body}"),
+                    Some(_) => eprintln!("updated download table for release {tag}"),
+                }
+            }
+            MuktiCommand::VerifyArtifacts {
+                base_url,
+                version,
+                jobs,
+                public_key,
+            } => {
+                let release_json = read_release_json(&self.json, false)?;
+                let mut options = VerifyOptions::new(&base_url);
+                options.version = version.as_ref();
+                options.jobs = jobs;
+                options.public_key = public_key.as_deref();
+                let report = verify_artifacts(&release_json, &options).await?;
+                for artifact in &report.artifacts {
+                    match &artifact.result {
+                        Ok(size) => {
+                            let size_note = if artifact.recorded_size.is_some() {
+                                "matches recorded size"
+                            } else {
+                                "no recorded size"
+                            };
+                            let signature_note = if artifact.signature_checked {
+                                "valid signature"
+                            } else {
+                                "signature not checked"
+                            };
+                            println!(
+                                "OK     {}.{} ({} bytes, {}, {})",
+                                artifact.target, artifact.format, size, size_note, signature_note
+                            );
+                        }
+                        Err(err) => {
+                            println!("FAILED {}.{}: {:#}", artifact.target, artifact.format, err);
+                        }
+                    }
+                }
+
+                let failed = report.failed_count();
+                if failed > 0 {
+                    bail!(
+                        "{}/{} artifacts for version {} failed verification",
+                        failed,
+                        report.artifacts.len(),
+                        report.version
+                    );
+                }
+                let unchecked = report
+                    .artifacts
+                    .iter()
+                    .filter(|artifact| !artifact.signature_checked)
+                    .count();
+                eprintln!(
+                    "verified {} artifacts for version {} ({} without a checked signature)",
+                    report.artifacts.len(),
+                    report.version,
+                    unchecked
+                );
+            }
+            MuktiCommand::Rollout { command } => {
+                let mut release_json = read_release_json(&self.json, false)?;
+                let event = match command {
+                    RolloutCommand::Start {
+                        version,
+                        percentage,
+                    } => start_rollout(&mut release_json, version, percentage)?,
+                    RolloutCommand::Advance { percentage } => {
+                        advance_rollout(&mut release_json, percentage)?
+                    }
+                    RolloutCommand::Abort => abort_rollout(&mut release_json)?,
+                    RolloutCommand::Clear => clear_rollout(&mut release_json)?,
+                };
+                match &event {
+                    ReleaseEventKind::RolloutStarted {
+                        version,
+                        previous,
+                        percentage,
+                    } => {
+                        eprintln!(
+                            "starting rollout of {version} at {percentage}% (previous: {previous})"
+                        );
+                    }
+                    ReleaseEventKind::RolloutAdvanced {
+                        version,
+                        percentage: 100,
+                    } => eprintln!("completing rollout of {version}"),
+                    ReleaseEventKind::RolloutAdvanced {
+                        version,
+                        percentage,
+                    } => eprintln!("advancing rollout of {version} to {percentage}%"),
+                    ReleaseEventKind::RolloutAborted { version } => {
+                        eprintln!(
+                            "aborting rollout of {version}, serving the previous version to all \
+                             requests"
+                        );
+                    }
+                    ReleaseEventKind::RolloutCleared { version } => {
+                        eprintln!("clearing rollout of {version}");
+                    }
+                    _ => {}
+                }
+                write_releases_json(&release_json, &self.json)?;
+                event_log.record(&release_json, event)?;
+            }
+            MuktiCommand::Validate => {
+                let release_json = read_release_json(&self.json, false)?;
+                release_json.validate()?;
+                eprintln!("{} is valid", self.json);
             }
             MuktiCommand::BackfillChecksums { jobs } => {
                 let mut release_json = read_release_json(&self.json, false)?;
-                backfill_checksums(&mut release_json, jobs).await;
+                let location_urls =
+                    backfill_checksums(&mut release_json, jobs, print_progress).await;
                 write_releases_json(&release_json, &self.json)?;
+                if !location_urls.is_empty() {
+                    event_log.record(
+                        &release_json,
+                        ReleaseEventKind::ChecksumsBackfilled { location_urls },
+                    )?;
+                }
             }
         }
 
@@ -116,65 +568,62 @@ impl MuktiApp {
     }
 }
 
-#[derive(Clone, Debug)]
-pub(crate) struct Archive {
-    pub(crate) target_format: TargetFormat,
-    pub(crate) name: String,
-}
-
-impl FromStr for Archive {
-    type Err = NameValueParseError;
-
-    fn from_str(input: &str) -> Result<Self, Self::Err> {
-        let (target_format, name) = name_value_parse(input, '=')?;
-        let target_format: TargetFormat = target_format.parse()?;
-        Ok(Self {
-            target_format,
-            name,
-        })
+fn print_progress(progress: ChecksumProgress<'_>) {
+    if let Some(e) = progress.error {
+        eprintln!("for {}, {:#}", progress.url, e);
     }
+    eprintln!(
+        "fetched {}/{} checksums, {} failed",
+        progress.succeeded, progress.total, progress.failed
+    );
 }
 
-#[derive(Clone, Debug)]
-pub(crate) struct Alias {
-    pub(crate) alias: String,
-    pub(crate) target_format: TargetFormat,
-}
-
-impl FromStr for Alias {
-    type Err = NameValueParseError;
+#[derive(Debug, Subcommand)]
+enum RolloutCommand {
+    /// Start serving a new latest version to a percentage of requests
+    Start {
+        /// Version to roll out: must be the latest version
+        #[clap(long = "version", required = true)]
+        version: Version,
 
-    fn from_str(input: &str) -> Result<Self, Self::Err> {
-        let (alias, target_format) = name_value_parse(input, '=')?;
-        let target_format: TargetFormat = target_format.parse()?;
-        Ok(Self {
-            alias,
-            target_format,
-        })
-    }
+        /// Percentage of requests to serve the new version to (1-99)
+        #[clap(long, required = true)]
+        percentage: u8,
+    },
+    /// Change the percentage of the rollout in progress, completing it at 100
+    Advance {
+        /// Percentage of requests to serve the new version to (1-100)
+        #[clap(long, required = true)]
+        percentage: u8,
+    },
+    /// Abort the rollout in progress, serving the previous version to all requests
+    Abort,
+    /// Remove a rollout that was aborted or superseded by a newer version
+    Clear,
 }
 
-#[derive(Clone, Debug)]
-pub(crate) struct TargetFormat {
-    pub(crate) target: String,
-    pub(crate) format: String,
-}
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum FlavorArg {
+    /// Netlify _redirects: purely static
+    Netlify,
 
-impl FromStr for TargetFormat {
-    type Err = NameValueParseError;
+    /// Cloudflare _redirects: uses :version splats along with some static redirects
+    Cloudflare,
 
-    fn from_str(input: &str) -> Result<Self, Self::Err> {
-        let (target, format) = name_value_parse(input, ':')?;
-        Ok(Self { target, format })
-    }
+    /// Netlify _redirects, plus an edge function for staged rollouts of latest
+    NetlifyEdge,
+
+    /// Cloudflare _redirects, plus a Pages Function for staged rollouts of latest
+    CloudflareEdge,
 }
 
-fn name_value_parse(input: &str, delimiter: char) -> Result<(String, String), NameValueParseError> {
-    match input.split_once(delimiter) {
-        Some((k, v)) => Ok((k.to_owned(), v.to_owned())),
-        None => Err(NameValueParseError {
-            input: input.to_owned(),
-            delimiter,
-        }),
+impl From<FlavorArg> for RedirectFlavor {
+    fn from(flavor: FlavorArg) -> Self {
+        match flavor {
+            FlavorArg::Netlify => Self::Netlify,
+            FlavorArg::Cloudflare => Self::Cloudflare,
+            FlavorArg::NetlifyEdge => Self::NetlifyEdge,
+            FlavorArg::CloudflareEdge => Self::CloudflareEdge,
+        }
     }
 }
@@ -1,10 +1,6 @@
 // Copyright (c) The mukti Contributors
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
-mod checksums;
 mod command;
-mod errors;
-mod redirects;
-mod release_json;
 
 pub use command::MuktiApp;
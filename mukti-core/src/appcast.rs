@@ -0,0 +1,253 @@
+// Copyright (c) The mukti Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Generate a Sparkle `appcast.xml` for macOS apps.
+
+use crate::{
+    output::{public_url, single_project, write_output_file, xml_escape},
+    targets::TargetFormat,
+};
+use camino::Utf8Path;
+use eyre::{bail, Result};
+use mukti_metadata::{MuktiReleasesJson, ReleaseStatus, ReleaseVersionData};
+use semver::Version;
+use std::fmt::Write as _;
+
+/// Options for appcast generation.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct AppcastOptions<'a> {
+    /// The base URL that redirects are served from, e.g. `https://get.nexte.st`.
+    pub base_url: &'a str,
+
+    /// The title of the appcast channel.
+    pub title: &'a str,
+
+    /// The target and format of the app archive.
+    pub target_format: &'a TargetFormat,
+
+    /// The Sparkle channel pre-release versions are published to.
+    pub prerelease_channel: &'a str,
+}
+
+impl<'a> AppcastOptions<'a> {
+    /// Creates options for the given archive, publishing pre-releases to the `beta` channel.
+    pub fn new(base_url: &'a str, title: &'a str, target_format: &'a TargetFormat) -> Self {
+        Self {
+            base_url,
+            title,
+            target_format,
+            prerelease_channel: "beta",
+        }
+    }
+}
+
+/// The result of appcast generation.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct GeneratedAppcast {
+    /// The number of items written.
+    pub item_count: usize,
+
+    /// Versions with no EdDSA signature recorded, which Sparkle won't install by default.
+    pub unsigned_versions: Vec<Version>,
+}
+
+/// Writes `appcast.xml` with one item per active version that has an artifact for the target and
+/// format.
+///
+/// Sparkle-specific data is read from each version's custom metadata, keyed by `TARGET.FORMAT`:
+///
+/// ```json
+/// "metadata": {
+///   "sparkle": {
+///     "universal-apple-darwin.dmg": { "ed-signature": "...", "length": 12345 }
+///   }
+/// }
+/// ```
+pub fn generate_appcast(
+    release_json: &MuktiReleasesJson,
+    options: &AppcastOptions<'_>,
+    out_dir: &Utf8Path,
+) -> Result<GeneratedAppcast> {
+    let project = single_project(release_json)?;
+    let TargetFormat { target, format } = options.target_format;
+    let metadata_key = format!("{}.{}", target, format);
+
+    let mut out = String::with_capacity(4096);
+    writeln!(out, r#"<?xml version="1.0" encoding="utf-8"?>"#)?;
+    writeln!(
+        out,
+        r#"<rss version="2.0" xmlns:sparkle="http://www.andymatuschak.org/xml-namespaces/sparkle">"#
+    )?;
+    writeln!(out, "  <channel>")?;
+    writeln!(out, "    <title>{}</title>", xml_escape(options.title))?;
+
+    let mut count = 0;
+    let mut unsigned_versions = Vec::new();
+    for (version, version_data) in project.all_versions() {
+        if version_data.status != ReleaseStatus::Active {
+            continue;
+        }
+        if !version_data
+            .locations
+            .iter()
+            .any(|location| &location.target == target && &location.format == format)
+        {
+            continue;
+        }
+
+        let sparkle = SparkleMetadata::from_version_data(version_data, &metadata_key);
+        if sparkle.ed_signature.is_none() {
+            unsigned_versions.push(version.clone());
+        }
+
+        let version_str = version.to_string();
+        writeln!(out, "    <item>")?;
+        writeln!(out, "      <title>{}</title>", xml_escape(&version_str))?;
+        writeln!(
+            out,
+            "      <link>{}</link>",
+            xml_escape(&version_data.release_url)
+        )?;
+        writeln!(
+            out,
+            "      <sparkle:version>{}</sparkle:version>",
+            xml_escape(&version_str)
+        )?;
+        writeln!(
+            out,
+            "      <sparkle:shortVersionString>{}</sparkle:shortVersionString>",
+            xml_escape(&version_str)
+        )?;
+        if !version.pre.is_empty() {
+            writeln!(
+                out,
+                "      <sparkle:channel>{}</sparkle:channel>",
+                xml_escape(options.prerelease_channel)
+            )?;
+        }
+
+        let url = public_url(options.base_url, version, target, format);
+        write!(
+            out,
+            r#"      <enclosure url="{}" type="application/octet-stream""#,
+            xml_escape(&url)
+        )?;
+        if let Some(length) = sparkle.length {
+            write!(out, r#" length="{}""#, length)?;
+        }
+        if let Some(signature) = sparkle.ed_signature {
+            write!(out, r#" sparkle:edSignature="{}""#, xml_escape(signature))?;
+        }
+        writeln!(out, " />")?;
+        writeln!(out, "    </item>")?;
+        count += 1;
+    }
+
+    writeln!(out, "  </channel>")?;
+    writeln!(out, "</rss>")?;
+
+    if count == 0 {
+        bail!("no active versions have artifacts for {}", metadata_key);
+    }
+    write_output_file(out_dir, "appcast.xml", &out)?;
+
+    Ok(GeneratedAppcast {
+        item_count: count,
+        unsigned_versions,
+    })
+}
+
+/// Sparkle data for one artifact, read from a version's custom metadata.
+#[derive(Debug, Default)]
+pub(crate) struct SparkleMetadata<'a> {
+    pub(crate) ed_signature: Option<&'a str>,
+    pub(crate) length: Option<u64>,
+}
+
+impl<'a> SparkleMetadata<'a> {
+    pub(crate) fn from_version_data(version_data: &'a ReleaseVersionData, key: &str) -> Self {
+        let Some(data) = version_data
+            .metadata
+            .get("sparkle")
+            .and_then(|sparkle| sparkle.get(key))
+        else {
+            return Self::default();
+        };
+
+        Self {
+            ed_signature: data.get("ed-signature").and_then(|v| v.as_str()),
+            length: data.get("length").and_then(|v| v.as_u64()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::{fixture_json, version_data_mut};
+
+    fn set_sparkle(json: &mut MuktiReleasesJson, version: &Version, sparkle: serde_json::Value) {
+        version_data_mut(json, version).metadata = serde_json::json!({
+            "sparkle": { "x86_64-unknown-linux-gnu.tar.gz": sparkle },
+        });
+    }
+
+    #[test]
+    fn test_generate_appcast() {
+        let mut json = fixture_json();
+        set_sparkle(
+            &mut json,
+            &Version::new(0, 5, 3),
+            serde_json::json!({ "ed-signature": "sig\"&<>", "length": 12345 }),
+        );
+        set_sparkle(
+            &mut json,
+            &Version::new(0, 5, 2),
+            serde_json::json!({ "ed-signature": "yanked-sig", "length": 1 }),
+        );
+
+        let dir = tempfile::tempdir().unwrap();
+        let out_dir = Utf8Path::from_path(dir.path()).unwrap();
+        let target_format: TargetFormat = "x86_64-unknown-linux-gnu:tar.gz".parse().unwrap();
+        let options = AppcastOptions::new("https://get.example.com/", "mukti & co", &target_format);
+        let mut generated = generate_appcast(&json, &options, out_dir).unwrap();
+        let contents = std::fs::read_to_string(out_dir.join("appcast.xml")).unwrap();
+
+        assert_eq!(generated.item_count, 3, "yanked 0.5.2 is skipped");
+        assert!(!contents.contains("0.5.2"), "{contents}");
+        assert!(contents.contains("<title>mukti &amp; co</title>"));
+
+        let item = |version: &str| {
+            contents
+                .split("<item>")
+                .find(|item| item.contains(&format!("<title>{version}</title>")))
+                .unwrap_or_else(|| panic!("item for {version} in:\n{contents}"))
+        };
+        assert!(
+            item("0.6.0-alpha.1").contains("<sparkle:channel>beta</sparkle:channel>"),
+            "pre-releases are published to the pre-release channel"
+        );
+        assert!(!item("0.5.3").contains("<sparkle:channel>"));
+        assert!(
+            item("0.5.3").contains(
+                r#"<enclosure url="https://get.example.com/0.5.3/x86_64-unknown-linux-gnu.tar.gz" type="application/octet-stream" length="12345" sparkle:edSignature="sig&quot;&amp;&lt;&gt;" />"#
+            ),
+            "length and escaped signature are emitted:\n{contents}"
+        );
+        assert!(
+            !item("0.5.1").contains("length=") && !item("0.5.1").contains("edSignature"),
+            "versions without sparkle metadata have no length or signature"
+        );
+
+        generated.unsigned_versions.sort();
+        assert_eq!(
+            generated.unsigned_versions,
+            [
+                Version::new(0, 5, 1),
+                "0.6.0-alpha.1".parse::<Version>().unwrap()
+            ],
+        );
+    }
+}
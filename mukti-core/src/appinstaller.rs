@@ -0,0 +1,231 @@
+// Copyright (c) The mukti Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Generate MSIX App Installer (`.appinstaller`) files.
+
+use crate::output::{public_url, resolve_version, single_project, write_output_file, xml_escape};
+use camino::Utf8Path;
+use eyre::{bail, Result};
+use mukti_metadata::MuktiReleasesJson;
+use semver::Version;
+use std::fmt::Write as _;
+
+/// Options for App Installer generation.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct AppInstallerOptions<'a> {
+    /// The base URL that redirects are served from, e.g. `https://get.nexte.st`.
+    pub base_url: &'a str,
+
+    /// The package name, matching the `Identity` element in the MSIX manifest.
+    pub package_name: &'a str,
+
+    /// The package publisher, matching the `Identity` element in the MSIX manifest.
+    pub publisher: &'a str,
+
+    /// The format of MSIX artifacts in the release JSON.
+    pub format: &'a str,
+
+    /// Hours between update checks when the app is launched.
+    pub hours_between_update_checks: u8,
+
+    /// Whether to prompt the user before updating.
+    pub show_prompt: bool,
+
+    /// Whether to block the app from launching until the update is installed.
+    pub update_blocks_activation: bool,
+}
+
+impl<'a> AppInstallerOptions<'a> {
+    /// Creates options for the given package, checking for updates every 24 hours without
+    /// prompting or blocking, for artifacts with the `msix` format.
+    pub fn new(base_url: &'a str, package_name: &'a str, publisher: &'a str) -> Self {
+        Self {
+            base_url,
+            package_name,
+            publisher,
+            format: "msix",
+            hours_between_update_checks: 24,
+            show_prompt: false,
+            update_blocks_activation: false,
+        }
+    }
+}
+
+/// The result of App Installer generation.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct GeneratedAppInstallers {
+    /// The version the files were written for.
+    pub version: Version,
+
+    /// The number of files written.
+    pub file_count: usize,
+}
+
+/// Writes one `<target>.appinstaller` file per MSIX artifact of the latest release.
+///
+/// Each App Installer file is expected to be served from `<base-url>/<target>.appinstaller`, and
+/// points at the versioned URL for the package, so Windows picks up new releases on the next
+/// update check once the files are regenerated.
+///
+pub fn generate_appinstallers(
+    release_json: &MuktiReleasesJson,
+    options: &AppInstallerOptions<'_>,
+    out_dir: &Utf8Path,
+) -> Result<GeneratedAppInstallers> {
+    let project = single_project(release_json)?;
+    let (version, version_data) = resolve_version(project, None)?;
+    let msix_version = msix_version(version)?;
+
+    let mut count = 0;
+    for location in version_data
+        .locations
+        .iter()
+        .filter(|location| location.format == options.format)
+    {
+        let arch = processor_architecture(&location.target)?;
+        let appinstaller_url = format!(
+            "{}/{}.appinstaller",
+            options.base_url.trim_end_matches('/'),
+            location.target
+        );
+        let package_url = public_url(
+            options.base_url,
+            version,
+            &location.target,
+            &location.format,
+        );
+
+        let mut out = String::with_capacity(1024);
+        writeln!(out, r#"<?xml version="1.0" encoding="utf-8"?>"#)?;
+        writeln!(out, "<!-- Generated by mukti for version {} -->", version)?;
+        writeln!(
+            out,
+            r#"<AppInstaller xmlns="http://schemas.microsoft.com/appx/appinstaller/2018" Version="{}" Uri="{}">"#,
+            msix_version,
+            xml_escape(&appinstaller_url),
+        )?;
+        writeln!(
+            out,
+            r#"  <MainPackage Name="{}" Publisher="{}" Version="{}" ProcessorArchitecture="{}" Uri="{}" />"#,
+            xml_escape(options.package_name),
+            xml_escape(options.publisher),
+            msix_version,
+            arch,
+            xml_escape(&package_url),
+        )?;
+        writeln!(out, "  <UpdateSettings>")?;
+        writeln!(
+            out,
+            r#"    <OnLaunch HoursBetweenUpdateChecks="{}" ShowPrompt="{}" UpdateBlocksActivation="{}" />"#,
+            options.hours_between_update_checks,
+            options.show_prompt,
+            options.update_blocks_activation,
+        )?;
+        writeln!(out, "    <AutomaticBackgroundTask />")?;
+        writeln!(out, "  </UpdateSettings>")?;
+        writeln!(out, "</AppInstaller>")?;
+
+        write_output_file(out_dir, &format!("{}.appinstaller", location.target), &out)?;
+        count += 1;
+    }
+
+    if count == 0 {
+        bail!(
+            "version {} has no artifacts with format `{}`",
+            version,
+            options.format
+        );
+    }
+
+    Ok(GeneratedAppInstallers {
+        version: version.clone(),
+        file_count: count,
+    })
+}
+
+/// Converts a semver version to the four-part version MSIX requires.
+fn msix_version(version: &Version) -> Result<String> {
+    if !version.pre.is_empty() {
+        bail!(
+            "MSIX versions cannot represent pre-release version {}",
+            version
+        );
+    }
+    for component in [version.major, version.minor, version.patch] {
+        if component > u64::from(u16::MAX) {
+            bail!(
+                "MSIX version components must be at most {}, found {} in {}",
+                u16::MAX,
+                component,
+                version
+            );
+        }
+    }
+
+    Ok(format!(
+        "{}.{}.{}.0",
+        version.major, version.minor, version.patch
+    ))
+}
+
+/// Maps the architecture in a target triple to an MSIX `ProcessorArchitecture`.
+fn processor_architecture(target: &str) -> Result<&'static str> {
+    let arch = target.split('-').next().unwrap_or(target);
+    let msix_arch = match arch {
+        "x86_64" => "x64",
+        "i586" | "i686" => "x86",
+        "aarch64" => "arm64",
+        "arm" | "armv7" | "thumbv7a" => "arm",
+        _ => bail!("unknown MSIX processor architecture for target {}", target),
+    };
+    Ok(msix_arch)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_msix_version() {
+        assert_eq!(
+            msix_version(&Version::new(0, 9, 66)).unwrap(),
+            "0.9.66.0",
+            "release versions get a zero revision"
+        );
+        assert!(
+            msix_version(&Version::parse("0.9.66-rc.1").unwrap()).is_err(),
+            "pre-release versions are rejected"
+        );
+        assert_eq!(
+            msix_version(&Version::new(1, 65535, 0)).unwrap(),
+            "1.65535.0.0",
+            "u16::MAX is a valid component"
+        );
+        assert!(
+            msix_version(&Version::new(1, 65536, 0)).is_err(),
+            "components that overflow u16 are rejected"
+        );
+    }
+
+    #[test]
+    fn test_processor_architecture() {
+        assert_eq!(
+            processor_architecture("x86_64-pc-windows-msvc").unwrap(),
+            "x64"
+        );
+        assert_eq!(
+            processor_architecture("i686-pc-windows-msvc").unwrap(),
+            "x86"
+        );
+        assert_eq!(
+            processor_architecture("aarch64-pc-windows-msvc").unwrap(),
+            "arm64"
+        );
+        assert!(
+            processor_architecture("riscv64gc-unknown-linux-gnu").is_err(),
+            "unknown architectures are rejected"
+        );
+    }
+}
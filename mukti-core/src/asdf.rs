@@ -0,0 +1,83 @@
+// Copyright (c) The mukti Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Generate plain-text endpoints for asdf and mise plugins.
+
+use crate::output::{active_versions_ascending, public_url, single_project, write_output_file};
+use camino::Utf8Path;
+use eyre::Result;
+use mukti_metadata::MuktiReleasesJson;
+use std::fmt::Write as _;
+
+/// Writes `asdf/versions` and `asdf/<version>/download-urls` for every active version.
+///
+/// `versions` lists one version per line in ascending order, which is what the `list-all` script
+/// of a plugin prints (after joining with spaces). Each `download-urls` file has one
+/// `TARGET FORMAT URL` line per artifact, so a plugin's `download` script can select a URL with
+/// `awk`.
+///
+/// Returns the number of versions written.
+pub fn generate_asdf(
+    release_json: &MuktiReleasesJson,
+    base_url: &str,
+    out_dir: &Utf8Path,
+) -> Result<usize> {
+    let project = single_project(release_json)?;
+
+    let versions = active_versions_ascending(project);
+
+    let mut versions_out = String::with_capacity(4096);
+    for (version, version_data) in &versions {
+        writeln!(versions_out, "{}", version)?;
+
+        let mut urls_out = String::with_capacity(1024);
+        for location in &version_data.locations {
+            writeln!(
+                urls_out,
+                "{} {} {}",
+                location.target,
+                location.format,
+                public_url(base_url, version, &location.target, &location.format)
+            )?;
+        }
+        write_output_file(
+            out_dir,
+            &format!("asdf/{}/download-urls", version),
+            &urls_out,
+        )?;
+    }
+    write_output_file(out_dir, "asdf/versions", &versions_out)?;
+
+    Ok(versions.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::fixture_json;
+
+    #[test]
+    fn test_generate_asdf() {
+        let dir = tempfile::tempdir().unwrap();
+        let out_dir = Utf8Path::from_path(dir.path()).unwrap();
+        let count = generate_asdf(&fixture_json(), "https://get.example.com/", out_dir).unwrap();
+
+        assert_eq!(count, 3, "yanked 0.5.2 is left out");
+        assert_eq!(
+            std::fs::read_to_string(out_dir.join("asdf/versions")).unwrap(),
+            "0.5.1\n0.5.3\n0.6.0-alpha.1\n",
+            "versions are in ascending semver order"
+        );
+        assert!(!out_dir.join("asdf/0.5.2").exists());
+
+        assert_eq!(
+            std::fs::read_to_string(out_dir.join("asdf/0.5.3/download-urls")).unwrap(),
+            "x86_64-unknown-linux-gnu tar.gz \
+             https://get.example.com/0.5.3/x86_64-unknown-linux-gnu.tar.gz\n\
+             x86_64-pc-windows-msvc tar.gz \
+             https://get.example.com/0.5.3/x86_64-pc-windows-msvc.tar.gz\n\
+             x86_64-pc-windows-msvc zip \
+             https://get.example.com/0.5.3/x86_64-pc-windows-msvc.zip\n",
+        );
+    }
+}
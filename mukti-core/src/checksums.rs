@@ -5,25 +5,57 @@ use std::collections::BTreeMap;
 
 use blake2::Blake2b;
 use bytes::Bytes;
-use color_eyre::{eyre::eyre, Result};
+use eyre::{eyre, Result};
 use futures_util::stream::StreamExt;
 use mukti_metadata::{Digest, DigestAlgorithm, MuktiReleasesJson, ReleaseLocation};
 use sha2::{Digest as _, Sha256};
-use tokio::task::JoinHandle;
+use tokio::task::{JoinError, JoinHandle};
 
-use crate::command::Archive;
+use crate::targets::Archive;
 
-pub(crate) struct ArchiveWithChecksums {
-    pub(crate) archive: Archive,
-    pub(crate) url: String,
-    // Err if fetching checksums failed.
-    pub(crate) checksums: Result<Checksums>,
+/// An archive along with the result of fetching its checksums.
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct ArchiveWithChecksums {
+    /// The archive that was fetched.
+    pub archive: Archive,
+
+    /// The URL the archive was fetched from.
+    pub url: String,
+
+    /// The checksums of the archive, or the error that occurred while fetching it.
+    pub checksums: Result<Checksums>,
+}
+
+/// Progress of fetching checksums, reported as each URL finishes.
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct ChecksumProgress<'a> {
+    /// The URL that finished.
+    pub url: &'a str,
+
+    /// The error that occurred while fetching the URL, if any.
+    pub error: Option<&'a eyre::Report>,
+
+    /// The number of URLs fetched successfully so far.
+    pub succeeded: usize,
+
+    /// The number of URLs that failed so far.
+    pub failed: usize,
+
+    /// The total number of URLs to fetch.
+    pub total: usize,
 }
 
-pub(crate) async fn fetch_release_checksums(
+/// Fetches each archive from `archive_prefix` and computes its checksums.
+///
+/// Results are returned in the same order as `archives`. Failures are recorded in the results
+/// rather than returned as errors.
+pub async fn fetch_release_checksums(
     archive_prefix: &str,
     archives: Vec<Archive>,
     download_jobs: usize,
+    mut on_progress: impl FnMut(ChecksumProgress<'_>),
 ) -> Vec<ArchiveWithChecksums> {
     let fetch_tasks = archives.iter().map(|archive| {
         let url = format!("{}/{}", archive_prefix, archive.name);
@@ -43,29 +75,19 @@ pub(crate) async fn fetch_release_checksums(
     let mut archives_with_checksums = Vec::new();
 
     while let Some((archive, url, result)) = stream.next().await {
-        let checksums = match result {
-            Ok(Ok(checksums)) => {
-                succeeded += 1;
-                Ok(checksums)
-            }
-            Ok(Err(e)) => {
-                failed += 1;
-                eprintln!("for {url}, error fetching checksum: {e}");
-                Err(eyre!(e))
-            }
-            Err(e) => {
-                failed += 1;
-                eprintln!("for {url}, error waiting on checksum task: {e}");
-                Err(eyre!(e))
-            }
-        };
+        let checksums = flatten_checksum_result(result);
+        match &checksums {
+            Ok(_) => succeeded += 1,
+            Err(_) => failed += 1,
+        }
 
-        eprintln!(
-            "fetched {}/{} checksums, {} failed",
+        on_progress(ChecksumProgress {
+            url: &url,
+            error: checksums.as_ref().err(),
             succeeded,
-            archives.len(),
-            failed
-        );
+            failed,
+            total: archives.len(),
+        });
         archives_with_checksums.push(ArchiveWithChecksums {
             archive: archive.clone(),
             url,
@@ -76,10 +98,18 @@ pub(crate) async fn fetch_release_checksums(
     archives_with_checksums
 }
 
-pub(crate) async fn backfill_checksums(release_json: &mut MuktiReleasesJson, download_jobs: usize) {
+/// Fetches and fills in missing checksums, returning the URLs of updated locations.
+pub async fn backfill_checksums(
+    release_json: &mut MuktiReleasesJson,
+    download_jobs: usize,
+    mut on_progress: impl FnMut(ChecksumProgress<'_>),
+) -> Vec<String> {
     let location_count = all_locations_without_checksums(release_json).count();
 
     let results = {
+        // The tasks own their locations rather than borrowing from release_json, which is
+        // borrowed mutably once all fetches are done.
+        #[allow(clippy::redundant_iter_cloned)]
         let fetch_tasks = all_locations_without_checksums(release_json)
             .cloned()
             .map(|location| {
@@ -99,26 +129,27 @@ pub(crate) async fn backfill_checksums(release_json: &mut MuktiReleasesJson, dow
         let mut failed = 0;
 
         while let Some((url, result)) = stream.next().await {
-            match result {
-                Ok(Ok(checksum)) => {
+            let error = match flatten_checksum_result(result) {
+                Ok(checksum) => {
                     // The checksum was fetched successfully -- update the release
                     // JSON with the new checksum.
-                    results.insert(url, checksum);
+                    results.insert(url.clone(), checksum);
                     succeeded += 1;
-                }
-                Ok(Err(e)) => {
-                    eprintln!("for {url}, error fetching checksum: {e}");
-                    failed += 1;
+                    None
                 }
                 Err(e) => {
-                    eprintln!("for {url}, error waiting on checksum task: {e}");
+                    failed += 1;
+                    Some(e)
                 }
-            }
-
-            eprintln!(
-                "fetched {}/{} checksums, {} failed",
-                succeeded, location_count, failed
-            );
+            };
+
+            on_progress(ChecksumProgress {
+                url: &url,
+                error: error.as_ref(),
+                succeeded,
+                failed,
+                total: location_count,
+            });
         }
 
         results
@@ -136,6 +167,18 @@ pub(crate) async fn backfill_checksums(release_json: &mut MuktiReleasesJson, dow
             }
         }
     }
+
+    results.into_keys().collect()
+}
+
+fn flatten_checksum_result(
+    result: Result<Result<Checksums, reqwest::Error>, JoinError>,
+) -> Result<Checksums> {
+    match result {
+        Ok(Ok(checksums)) => Ok(checksums),
+        Ok(Err(e)) => Err(eyre!(e).wrap_err("error fetching checksum")),
+        Err(e) => Err(eyre!(e).wrap_err("error waiting on checksum task")),
+    }
 }
 
 fn all_locations_without_checksums(
@@ -164,7 +207,6 @@ fn spawn_fetch_and_checksum_task(url: String) -> JoinHandle<Result<Checksums, re
                 match fetch_url(&url).await {
                     Ok(bytes) => break bytes,
                     Err(e) => {
-                        eprintln!("Error fetching checksum: {}", e);
                         if attempt == 2 {
                             return Err(e);
                         }
@@ -174,13 +216,7 @@ fn spawn_fetch_and_checksum_task(url: String) -> JoinHandle<Result<Checksums, re
             }
         };
 
-        let sha256 = Sha256::digest(&bytes);
-        let blake2b = Blake2b::digest(&bytes);
-
-        Ok(Checksums {
-            sha256: sha256.into(),
-            blake2b: blake2b.into(),
-        })
+        Ok(Checksums::from_bytes(&bytes))
     })
 }
 
@@ -189,13 +225,24 @@ async fn fetch_url(url: &str) -> reqwest::Result<Bytes> {
     resp.bytes().await
 }
 
-pub(crate) struct Checksums {
+/// The checksums mukti computes for an artifact.
+#[derive(Clone, Debug)]
+pub struct Checksums {
     sha256: [u8; 32],
     blake2b: [u8; 64],
 }
 
 impl Checksums {
-    pub(crate) fn to_checksum_map(&self) -> BTreeMap<DigestAlgorithm, Digest> {
+    /// Computes checksums for the contents of an artifact.
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        Self {
+            sha256: Sha256::digest(bytes).into(),
+            blake2b: Blake2b::digest(bytes).into(),
+        }
+    }
+
+    /// Returns hex-encoded checksums keyed by algorithm, as stored in the release JSON.
+    pub fn to_checksum_map(&self) -> BTreeMap<DigestAlgorithm, Digest> {
         [
             (DigestAlgorithm::SHA256, Digest(hex::encode(self.sha256))),
             (DigestAlgorithm::BLAKE2B, Digest(hex::encode(self.blake2b))),
@@ -0,0 +1,320 @@
+// Copyright (c) The mukti Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Generate Chocolatey package sources.
+
+use crate::{
+    output::{public_url, resolve_version, single_project, write_output_file, xml_escape},
+    targets::TargetFormat,
+};
+use camino::Utf8Path;
+use eyre::{bail, eyre, Result};
+use mukti_metadata::{DigestAlgorithm, MuktiReleasesJson};
+use semver::Version;
+use std::fmt::Write as _;
+
+/// Options for Chocolatey package generation.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct ChocolateyOptions<'a> {
+    /// The base URL that redirects are served from, e.g. `https://get.nexte.st`.
+    pub base_url: &'a str,
+
+    /// The Chocolatey package ID, also used for the name of the `.nuspec` file.
+    pub package_id: &'a str,
+
+    /// The package authors.
+    pub authors: &'a str,
+
+    /// The package description.
+    pub description: &'a str,
+
+    /// The project URL, if any.
+    pub project_url: Option<&'a str>,
+
+    /// The target and format of the Windows artifact.
+    pub target_format: &'a TargetFormat,
+
+    /// The version to package, or `None` for the latest version.
+    pub version: Option<&'a Version>,
+}
+
+impl<'a> ChocolateyOptions<'a> {
+    /// Creates options for packaging the latest version, without a project URL.
+    pub fn new(
+        base_url: &'a str,
+        package_id: &'a str,
+        authors: &'a str,
+        description: &'a str,
+        target_format: &'a TargetFormat,
+    ) -> Self {
+        Self {
+            base_url,
+            package_id,
+            authors,
+            description,
+            project_url: None,
+            target_format,
+            version: None,
+        }
+    }
+}
+
+/// Writes `<package-id>.nuspec` and `tools/chocolateyInstall.ps1` for a release.
+///
+/// The output directory can be passed directly to `choco pack`. Returns the version that was
+/// packaged.
+pub fn generate_chocolatey(
+    release_json: &MuktiReleasesJson,
+    options: &ChocolateyOptions<'_>,
+    out_dir: &Utf8Path,
+) -> Result<Version> {
+    let project = single_project(release_json)?;
+    let (version, version_data) = resolve_version(project, options.version)?;
+    let TargetFormat { target, format } = options.target_format;
+
+    // Chocolatey distinguishes between 32-bit and 64-bit x86 URLs, and doesn't support other
+    // architectures.
+    let suffix = match target.split('-').next().unwrap_or_default() {
+        "x86_64" => "64",
+        "i586" | "i686" => "",
+        arch => bail!(
+            "unsupported Chocolatey architecture `{}` for target {} \
+             (expected x86_64, i586 or i686)",
+            arch,
+            target
+        ),
+    };
+
+    let location = version_data
+        .locations
+        .iter()
+        .find(|location| &location.target == target && &location.format == format)
+        .ok_or_else(|| {
+            eyre!(
+                "version {} has no artifact for {}:{}",
+                version,
+                target,
+                format
+            )
+        })?;
+    let checksum = location
+        .checksums
+        .get(&DigestAlgorithm::SHA256)
+        .ok_or_else(|| {
+            eyre!(
+                "version {} has no SHA-256 checksum for {}:{} (run backfill-checksums first)",
+                version,
+                target,
+                format
+            )
+        })?;
+
+    let mut nuspec = String::with_capacity(1024);
+    writeln!(nuspec, r#"<?xml version="1.0" encoding="utf-8"?>"#)?;
+    writeln!(
+        nuspec,
+        r#"<package xmlns="http://schemas.microsoft.com/packaging/2015/06/nuspec.xsd">"#
+    )?;
+    writeln!(nuspec, "  <metadata>")?;
+    writeln!(nuspec, "    <id>{}</id>", xml_escape(options.package_id))?;
+    writeln!(nuspec, "    <version>{}</version>", version)?;
+    writeln!(
+        nuspec,
+        "    <authors>{}</authors>",
+        xml_escape(options.authors)
+    )?;
+    if let Some(project_url) = options.project_url {
+        writeln!(
+            nuspec,
+            "    <projectUrl>{}</projectUrl>",
+            xml_escape(project_url)
+        )?;
+    }
+    writeln!(
+        nuspec,
+        "    <releaseNotes>{}</releaseNotes>",
+        xml_escape(&version_data.release_url)
+    )?;
+    writeln!(
+        nuspec,
+        "    <description>{}</description>",
+        xml_escape(options.description)
+    )?;
+    writeln!(nuspec, "  </metadata>")?;
+    writeln!(nuspec, "  <files>")?;
+    writeln!(nuspec, r#"    <file src="tools\**" target="tools" />"#)?;
+    writeln!(nuspec, "  </files>")?;
+    writeln!(nuspec, "</package>")?;
+
+    let url = public_url(options.base_url, version, target, format);
+
+    let mut install = String::with_capacity(1024);
+    writeln!(install, "# Generated by mukti for version {}", version)?;
+    writeln!(install, "$ErrorActionPreference = 'Stop'")?;
+    writeln!(
+        install,
+        "$toolsDir = \"$(Split-Path -Parent $MyInvocation.MyCommand.Definition)\""
+    )?;
+    writeln!(install)?;
+    writeln!(install, "$packageArgs = @{{")?;
+    writeln!(install, "  packageName = $env:ChocolateyPackageName")?;
+    writeln!(install, "  url{} = {}", suffix, ps_quote(&url))?;
+    writeln!(install, "  checksum{} = {}", suffix, ps_quote(&checksum.0))?;
+    writeln!(install, "  checksumType{} = 'sha256'", suffix)?;
+    match format.as_str() {
+        "zip" => {
+            writeln!(install, "  unzipLocation = $toolsDir")?;
+            writeln!(install, "}}")?;
+            writeln!(install)?;
+            writeln!(install, "Install-ChocolateyZipPackage @packageArgs")?;
+        }
+        "msi" => {
+            writeln!(install, "  fileType = 'msi'")?;
+            writeln!(install, "  silentArgs = '/qn /norestart'")?;
+            writeln!(install, "  validExitCodes = @(0, 3010, 1641)")?;
+            writeln!(install, "}}")?;
+            writeln!(install)?;
+            writeln!(install, "Install-ChocolateyPackage @packageArgs")?;
+        }
+        other => bail!(
+            "unsupported Chocolatey archive format `{}` (expected zip or msi)",
+            other
+        ),
+    }
+
+    write_output_file(out_dir, &format!("{}.nuspec", options.package_id), &nuspec)?;
+    write_output_file(out_dir, "tools/chocolateyInstall.ps1", &install)?;
+
+    Ok(version.clone())
+}
+
+/// Quotes a string as a PowerShell single-quoted literal.
+fn ps_quote(input: &str) -> String {
+    format!("'{}'", input.replace('\'', "''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::{fixture_json, version_data_mut};
+    use mukti_metadata::{Digest, ReleaseLocation};
+
+    /// Returns the fixture with artifacts for `targets` added to 0.5.3, each with a SHA-256
+    /// checksum.
+    fn fixture_with_artifacts(targets: &[(&str, &str)]) -> MuktiReleasesJson {
+        let mut json = fixture_json();
+        let locations = &mut version_data_mut(&mut json, &Version::new(0, 5, 3)).locations;
+        locations.retain(|location| location.format == "tar.gz");
+        for (target, format) in targets {
+            locations.push(ReleaseLocation {
+                target: (*target).to_owned(),
+                format: (*format).to_owned(),
+                url: format!("https://example.com/{target}.{format}"),
+                checksums: [(DigestAlgorithm::SHA256, Digest(format!("{target}-sha256")))]
+                    .into_iter()
+                    .collect(),
+            });
+        }
+        json
+    }
+
+    fn generate(json: &MuktiReleasesJson, target_format: &str) -> Result<(String, String)> {
+        let dir = tempfile::tempdir().unwrap();
+        let out_dir = Utf8Path::from_path(dir.path()).unwrap();
+        let target_format: TargetFormat = target_format.parse().unwrap();
+        let mut options = ChocolateyOptions::new(
+            "https://get.example.com",
+            "mukti",
+            "mukti & co",
+            "Release manager",
+            &target_format,
+        );
+        options.project_url = Some("https://example.com/mukti");
+        generate_chocolatey(json, &options, out_dir)?;
+
+        let nuspec = std::fs::read_to_string(out_dir.join("mukti.nuspec")).unwrap();
+        let install = std::fs::read_to_string(out_dir.join("tools/chocolateyInstall.ps1")).unwrap();
+        Ok((nuspec, install))
+    }
+
+    #[test]
+    fn test_generate_chocolatey() {
+        let json = fixture_with_artifacts(&[
+            ("x86_64-pc-windows-msvc", "zip"),
+            ("x86_64-pc-windows-msvc", "msi"),
+            ("i686-pc-windows-msvc", "zip"),
+            ("aarch64-pc-windows-msvc", "zip"),
+        ]);
+
+        let (nuspec, install) = generate(&json, "x86_64-pc-windows-msvc:zip").unwrap();
+        for line in [
+            "    <id>mukti</id>",
+            "    <version>0.5.3</version>",
+            "    <authors>mukti &amp; co</authors>",
+            "    <projectUrl>https://example.com/mukti</projectUrl>",
+            "    <releaseNotes>https://my-release-url/version-0.5.3</releaseNotes>",
+            "    <description>Release manager</description>",
+        ] {
+            assert!(nuspec.lines().any(|l| l == line), "{line} is in:\n{nuspec}");
+        }
+        for line in [
+            "  url64 = 'https://get.example.com/0.5.3/x86_64-pc-windows-msvc.zip'",
+            "  checksum64 = 'x86_64-pc-windows-msvc-sha256'",
+            "  checksumType64 = 'sha256'",
+            "  unzipLocation = $toolsDir",
+            "Install-ChocolateyZipPackage @packageArgs",
+        ] {
+            assert!(
+                install.lines().any(|l| l == line),
+                "{line} is in:\n{install}"
+            );
+        }
+
+        let (_, install) = generate(&json, "x86_64-pc-windows-msvc:msi").unwrap();
+        for line in [
+            "  url64 = 'https://get.example.com/0.5.3/x86_64-pc-windows-msvc.msi'",
+            "  fileType = 'msi'",
+            "  silentArgs = '/qn /norestart'",
+            "Install-ChocolateyPackage @packageArgs",
+        ] {
+            assert!(
+                install.lines().any(|l| l == line),
+                "{line} is in:\n{install}"
+            );
+        }
+
+        let (_, install) = generate(&json, "i686-pc-windows-msvc:zip").unwrap();
+        assert!(
+            install
+                .lines()
+                .any(|l| l == "  url = 'https://get.example.com/0.5.3/i686-pc-windows-msvc.zip'"),
+            "32-bit targets use unsuffixed keys:\n{install}"
+        );
+
+        let err = generate(&json, "aarch64-pc-windows-msvc:zip").unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("unsupported Chocolatey architecture"),
+            "ARM64 isn't installed on x64 machines: {err}"
+        );
+        assert!(
+            generate(&json, "x86_64-unknown-linux-gnu:tar.gz").is_err(),
+            "tar.gz isn't supported"
+        );
+    }
+
+    #[test]
+    fn test_ps_quote() {
+        assert_eq!(ps_quote(""), "''");
+        assert_eq!(
+            ps_quote("https://get.nexte.st/0.9.66/x86_64-pc-windows-msvc.zip"),
+            "'https://get.nexte.st/0.9.66/x86_64-pc-windows-msvc.zip'"
+        );
+        assert_eq!(
+            ps_quote("it's $env:USERNAME"),
+            "'it''s $env:USERNAME'",
+            "single quotes are doubled and nothing else is expanded"
+        );
+    }
+}
@@ -0,0 +1,104 @@
+// Copyright (c) The mukti Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Configuration read from `mukti.toml`.
+
+use camino::Utf8Path;
+use eyre::{Result, WrapErr};
+use serde::Deserialize;
+
+/// The contents of a `mukti.toml` file.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+#[non_exhaustive]
+pub struct MuktiConfig {
+    /// Additional static redirects to emit alongside the generated ones.
+    #[serde(default)]
+    pub redirects: Vec<ExtraRedirect>,
+
+    /// Preferred formats for extension-less `/<version>/<target>` redirects.
+    ///
+    /// These redirects are only generated if a `[default-formats]` table is present.
+    #[serde(default)]
+    pub default_formats: Option<DefaultFormats>,
+}
+
+impl MuktiConfig {
+    /// Reads the config file, returning the default config if it doesn't exist.
+    pub fn from_path(path: &Utf8Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let contents = std::fs::read_to_string(path)
+            .wrap_err_with(|| format!("failed to read config file at {}", path))?;
+        toml::from_str(&contents)
+            .wrap_err_with(|| format!("failed to parse config file at {}", path))
+    }
+}
+
+/// A static redirect declared in `mukti.toml`.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct ExtraRedirect {
+    /// The path to redirect from, relative to the redirect prefix.
+    pub from: String,
+
+    /// The URL or path to redirect to.
+    pub to: String,
+
+    /// The HTTP status code for the redirect. Rewrites (status 200) must be to a path.
+    #[serde(default = "default_status")]
+    pub status: u16,
+}
+
+fn default_status() -> u16 {
+    302
+}
+
+/// Formats to redirect extension-less paths to, in order of preference.
+///
+/// The first format in the list that a target has an artifact for is used. An empty list disables
+/// these redirects for the platform.
+///
+/// Lists that aren't specified default to `["zip", "msi"]` for Windows and `["tar.gz"]` for other
+/// platforms.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct DefaultFormats {
+    /// Preferred formats for Windows targets.
+    #[serde(default = "default_windows_formats")]
+    pub windows: Vec<String>,
+
+    /// Preferred formats for all other targets.
+    #[serde(default = "default_unix_formats")]
+    pub unix: Vec<String>,
+}
+
+impl DefaultFormats {
+    /// Returns the preferred formats for a target triple.
+    pub fn for_target(&self, target: &str) -> &[String] {
+        if target.contains("-windows") {
+            &self.windows
+        } else {
+            &self.unix
+        }
+    }
+}
+
+impl Default for DefaultFormats {
+    fn default() -> Self {
+        Self {
+            windows: default_windows_formats(),
+            unix: default_unix_formats(),
+        }
+    }
+}
+
+fn default_windows_formats() -> Vec<String> {
+    vec!["zip".to_owned(), "msi".to_owned()]
+}
+
+fn default_unix_formats() -> Vec<String> {
+    vec!["tar.gz".to_owned()]
+}
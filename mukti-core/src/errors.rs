@@ -0,0 +1,108 @@
+// Copyright (c) The mukti Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use semver::Version;
+use std::{error, fmt};
+use thiserror::Error;
+
+/// A command-line value wasn't of the form `NAME<delimiter>VALUE`.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct NameValueParseError {
+    /// The value that failed to parse.
+    pub input: String,
+
+    /// The delimiter that was expected between the name and the value.
+    pub delimiter: char,
+}
+
+impl fmt::Display for NameValueParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "unable to parse '{}' in the format NAME{}VALUE",
+            self.input, self.delimiter,
+        )
+    }
+}
+
+impl error::Error for NameValueParseError {}
+
+/// The releases JSON doesn't contain exactly one project.
+#[derive(Clone, Debug, Error)]
+#[non_exhaustive]
+#[error("mukti currently only supports one project, {count} found")]
+pub struct ProjectCountError {
+    /// The number of projects found.
+    pub count: usize,
+}
+
+/// An error that occurred while changing the rollout of the latest version.
+#[derive(Clone, Debug, Error)]
+#[non_exhaustive]
+pub enum RolloutError {
+    /// The releases JSON doesn't contain exactly one project.
+    #[error(transparent)]
+    ProjectCount(#[from] ProjectCountError),
+
+    /// The rollout percentage is out of range.
+    #[error("rollout percentage must be between 1 and {max}, found {percentage}")]
+    InvalidPercentage {
+        /// The percentage that was requested.
+        percentage: u8,
+        /// The largest percentage allowed.
+        max: u8,
+    },
+
+    /// The version to roll out wasn't found.
+    #[error("version {version} not found in releases JSON")]
+    VersionNotFound {
+        /// The version to roll out.
+        version: Version,
+    },
+
+    /// The version to roll out isn't the latest version.
+    #[error("only the latest version ({latest}) can be rolled out, not {version}")]
+    NotLatest {
+        /// The version to roll out.
+        version: Version,
+        /// The latest version.
+        latest: Version,
+    },
+
+    /// There's no earlier active version to serve to requests outside the rollout.
+    #[error("no earlier version to roll out {version} from")]
+    NoPreviousVersion {
+        /// The version to roll out.
+        version: Version,
+    },
+
+    /// No rollout was found.
+    #[error("no rollout found in releases JSON")]
+    NoRollout,
+
+    /// A rollout is in progress.
+    #[error("a rollout of {version} is in progress (complete or abort it first)")]
+    InProgress {
+        /// The version being rolled out.
+        version: Version,
+    },
+
+    /// The rollout was aborted.
+    #[error("rollout of {version} was aborted (start it again to resume it)")]
+    Aborted {
+        /// The version that was being rolled out.
+        version: Version,
+    },
+
+    /// The rollout was aborted, but its version is still the latest one, so clearing the rollout
+    /// would serve it to all requests.
+    #[error(
+        "{version} is still the latest version, so clearing its aborted rollout would serve it \
+         to all requests (release a newer version first)"
+    )]
+    LatestAborted {
+        /// The version that was being rolled out.
+        version: Version,
+    },
+}
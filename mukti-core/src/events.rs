@@ -0,0 +1,132 @@
+// Copyright (c) The mukti Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Append events to the release event log.
+
+use camino::Utf8PathBuf;
+use eyre::{Result, WrapErr};
+use mukti_metadata::{MuktiReleasesJson, ReleaseEvent, ReleaseEventKind};
+use std::{fs::OpenOptions, io::Write as _, time::SystemTime};
+
+/// An optional, append-only log of changes made to the release JSON.
+#[derive(Clone, Debug)]
+pub struct EventLog {
+    path: Option<Utf8PathBuf>,
+    actor: Option<String>,
+}
+
+impl EventLog {
+    /// Creates an event log that appends to `path`, attributing events to `actor`.
+    ///
+    /// If `path` is `None`, events aren't recorded.
+    pub fn new(path: Option<Utf8PathBuf>, actor: Option<String>) -> Self {
+        Self { path, actor }
+    }
+
+    /// Appends an event to the log, if one is configured.
+    ///
+    /// This should be called after the release JSON has been written out.
+    pub fn record(&self, release_json: &MuktiReleasesJson, kind: ReleaseEventKind) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+
+        let event = ReleaseEvent {
+            timestamp: humantime::format_rfc3339_seconds(SystemTime::now()).to_string(),
+            actor: self.actor.clone(),
+            project: release_json
+                .projects
+                .keys()
+                .next()
+                .cloned()
+                .unwrap_or_default(),
+            kind,
+        };
+        let mut line = serde_json::to_string(&event)?;
+        line.push('\n');
+
+        // A single write call for the whole line keeps concurrent appends from interleaving.
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .wrap_err_with(|| format!("failed to open event log at {}", path))?;
+        file.write_all(line.as_bytes())
+            .wrap_err_with(|| format!("failed to append to event log at {}", path))?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::fixture_json;
+    use mukti_metadata::ReleaseEvent;
+    use semver::Version;
+
+    #[test]
+    fn test_record() {
+        let json = fixture_json();
+        let dir = tempfile::tempdir().unwrap();
+        let dir_path = Utf8PathBuf::from_path_buf(dir.path().to_owned()).unwrap();
+        let path = dir_path.join("events.ndjson");
+
+        let event_log = EventLog::new(Some(path.clone()), Some("octocat".to_owned()));
+        event_log
+            .record(
+                &json,
+                ReleaseEventKind::RolloutStarted {
+                    version: Version::new(0, 5, 3),
+                    previous: Version::new(0, 5, 1),
+                    percentage: 10,
+                },
+            )
+            .unwrap();
+        event_log
+            .record(
+                &json,
+                ReleaseEventKind::RolloutAborted {
+                    version: Version::new(0, 5, 3),
+                },
+            )
+            .unwrap();
+
+        EventLog::new(None, Some("octocat".to_owned()))
+            .record(
+                &json,
+                ReleaseEventKind::RolloutAborted {
+                    version: Version::new(0, 5, 3),
+                },
+            )
+            .unwrap();
+        assert_eq!(
+            std::fs::read_dir(&dir_path).unwrap().count(),
+            1,
+            "a log without a path writes nothing"
+        );
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        assert!(contents.ends_with('\n'), "every line is terminated");
+        let events: Vec<ReleaseEvent> = contents
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(events.len(), 2, "one line per event:\n{contents}");
+        for event in &events {
+            assert_eq!(event.actor.as_deref(), Some("octocat"));
+            assert_eq!(event.project, "mukti");
+        }
+        assert!(
+            matches!(
+                events[0].kind,
+                ReleaseEventKind::RolloutStarted { percentage: 10, .. }
+            ),
+            "events are appended in order"
+        );
+        assert!(matches!(
+            events[1].kind,
+            ReleaseEventKind::RolloutAborted { .. }
+        ));
+    }
+}
@@ -0,0 +1,228 @@
+// Copyright (c) The mukti Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Annotate GitHub releases with download links.
+
+use crate::output::{public_url, resolve_version, single_project};
+use eyre::{bail, eyre, Result, WrapErr};
+use mukti_metadata::{DigestAlgorithm, MuktiReleasesJson, ReleaseVersionData};
+use semver::Version;
+use std::fmt::Write as _;
+
+const START_MARKER: &str = "<!-- mukti-downloads-start -->";
+const END_MARKER: &str = "<!-- mukti-downloads-end -->";
+
+/// Options for annotating a GitHub release.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct AnnotateOptions<'a> {
+    /// The GitHub API URL.
+    pub api_url: &'a str,
+
+    /// The GitHub repository, in the format `OWNER/NAME`.
+    pub repo: &'a str,
+
+    /// The release tag to annotate.
+    pub tag: &'a str,
+
+    /// The version the tag corresponds to, or `None` to parse it from the tag.
+    pub version: Option<&'a Version>,
+
+    /// The base URL that redirects are served from, e.g. `https://get.nexte.st`.
+    pub base_url: &'a str,
+
+    /// Whether to return the new release body without updating the release.
+    pub dry_run: bool,
+
+    /// The user agent sent to the GitHub API. Tools built on this crate should set it to their
+    /// own name and version.
+    pub user_agent: &'a str,
+}
+
+impl<'a> AnnotateOptions<'a> {
+    /// Creates options for annotating a release on github.com, parsing the version from the tag.
+    ///
+    /// The user agent defaults to `mukti-core/VERSION`.
+    pub fn new(repo: &'a str, tag: &'a str, base_url: &'a str) -> Self {
+        Self {
+            api_url: "https://api.github.com",
+            repo,
+            tag,
+            version: None,
+            base_url,
+            dry_run: false,
+            user_agent: concat!("mukti-core/", env!("CARGO_PKG_VERSION")),
+        }
+    }
+}
+
+/// Appends or updates a table of download links in the body of a GitHub release.
+///
+/// The table is delimited by HTML comments, so subsequent runs replace it in place rather than
+/// appending another copy. `token` must have write access to releases. It may be `None` if
+/// `options.dry_run` is set, which only reads the release and works for public repositories
+/// without a token.
+///
+/// Returns the new release body, or `None` if the release was already up to date. If
+/// `options.dry_run` is set, the release isn't updated.
+pub async fn annotate_github_release(
+    release_json: &MuktiReleasesJson,
+    options: &AnnotateOptions<'_>,
+    token: Option<&str>,
+) -> Result<Option<String>> {
+    if token.is_none() && !options.dry_run {
+        bail!("a token is required to update GitHub releases");
+    }
+    let project = single_project(release_json)?;
+    let version = match options.version {
+        Some(version) => version.clone(),
+        None => version_from_tag(options.tag)?,
+    };
+    let (version, version_data) = resolve_version(project, Some(&version))?;
+    let table = download_table(options.base_url, version, version_data)?;
+
+    let client = reqwest::Client::builder()
+        .user_agent(options.user_agent)
+        .build()?;
+    let api_url = options.api_url.trim_end_matches('/');
+
+    let release: serde_json::Value = {
+        let url = format!(
+            "{}/repos/{}/releases/tags/{}",
+            api_url, options.repo, options.tag
+        );
+        let mut request = client.get(&url);
+        if let Some(token) = token {
+            request = request.bearer_auth(token);
+        }
+        let bytes = request
+            .header("Accept", "application/vnd.github+json")
+            .send()
+            .await
+            .and_then(|resp| resp.error_for_status())
+            .wrap_err_with(|| format!("failed to fetch GitHub release at {}", url))?
+            .bytes()
+            .await?;
+        serde_json::from_slice(&bytes).wrap_err("failed to deserialize GitHub release")?
+    };
+    let id = release["id"]
+        .as_u64()
+        .ok_or_else(|| eyre!("GitHub release for tag {} has no id", options.tag))?;
+    let body = release["body"].as_str().unwrap_or_default();
+
+    let new_body = update_release_body(body, &table);
+    if new_body == body {
+        return Ok(None);
+    }
+    if options.dry_run {
+        return Ok(Some(new_body));
+    }
+    let token = token.expect("a token is required unless this is a dry run");
+
+    let url = format!("{}/repos/{}/releases/{}", api_url, options.repo, id);
+    client
+        .patch(&url)
+        .bearer_auth(token)
+        .header("Accept", "application/vnd.github+json")
+        .header("Content-Type", "application/json")
+        .body(serde_json::to_vec(
+            &serde_json::json!({ "body": new_body }),
+        )?)
+        .send()
+        .await
+        .and_then(|resp| resp.error_for_status())
+        .wrap_err_with(|| format!("failed to update GitHub release at {}", url))?;
+
+    Ok(Some(new_body))
+}
+
+/// Parses a version out of a tag like `v1.2.3` or `my-project-1.2.3`.
+fn version_from_tag(tag: &str) -> Result<Version> {
+    let version_str = tag.trim_start_matches(|c: char| !c.is_ascii_digit());
+    match version_str.parse() {
+        Ok(version) => Ok(version),
+        Err(_) => bail!(
+            "unable to determine version from tag `{}` (pass --version explicitly)",
+            tag
+        ),
+    }
+}
+
+fn download_table(
+    base_url: &str,
+    version: &Version,
+    version_data: &ReleaseVersionData,
+) -> Result<String> {
+    let mut out = String::with_capacity(1024);
+    writeln!(out, "{}", START_MARKER)?;
+    writeln!(out, "## Downloads")?;
+    writeln!(out)?;
+    writeln!(out, "| Target | Format | Download | SHA-256 |")?;
+    writeln!(out, "| --- | --- | --- | --- |")?;
+    for location in &version_data.locations {
+        let url = public_url(base_url, version, &location.target, &location.format);
+        let checksum = match location.checksums.get(&DigestAlgorithm::SHA256) {
+            Some(digest) => format!("`{}`", digest.0),
+            None => "-".to_owned(),
+        };
+        writeln!(
+            out,
+            "| {} | {} | [download]({}) | {} |",
+            location.target, location.format, url, checksum
+        )?;
+    }
+    write!(out, "{}", END_MARKER)?;
+
+    Ok(out)
+}
+
+/// Replaces the download table in `body` if present, or appends it otherwise.
+fn update_release_body(body: &str, table: &str) -> String {
+    if let Some(start) = body.find(START_MARKER) {
+        if let Some(end) = body[start..].find(END_MARKER) {
+            let end = start + end + END_MARKER.len();
+            return format!("{}{}{}", &body[..start], table, &body[end..]);
+        }
+    }
+
+    if body.trim().is_empty() {
+        table.to_owned()
+    } else {
+        format!("{}\n\n{}", body.trim_end(), table)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_version_from_tag() {
+        assert_eq!(version_from_tag("v1.2.3").unwrap(), Version::new(1, 2, 3));
+        assert_eq!(
+            version_from_tag("mukti-bin-0.6.2").unwrap(),
+            Version::new(0, 6, 2)
+        );
+        assert!(version_from_tag("latest").is_err());
+    }
+
+    #[test]
+    fn test_update_release_body() {
+        let table = format!("{START_MARKER}\ntable\n{END_MARKER}");
+
+        assert_eq!(update_release_body("", &table), table, "empty body");
+        assert_eq!(
+            update_release_body("Release notes\n", &table),
+            format!("Release notes\n\n{table}"),
+            "table is appended"
+        );
+        assert_eq!(
+            update_release_body(
+                &format!("Notes\n\n{START_MARKER}\nold\n{END_MARKER}\n\nFooter"),
+                &table
+            ),
+            format!("Notes\n\n{table}\n\nFooter"),
+            "existing table is replaced in place"
+        );
+    }
+}
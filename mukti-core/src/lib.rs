@@ -0,0 +1,55 @@
+// Copyright (c) The mukti Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Core logic for mukti: updating release metadata, deriving redirects from it, and generating
+//! files for package managers and update frameworks.
+//!
+//! The `mukti-bin` command is a thin wrapper around this crate.
+//!
+//! # Errors
+//!
+//! Functions that change release metadata in memory return typed errors: [`RolloutError`] for
+//! rollouts and [`ProjectCountError`] for [`add_release`]. All other functions return
+//! [`eyre::Report`], which is meant to be displayed rather than matched on, so `eyre` is a public
+//! dependency of this crate.
+
+#![warn(missing_docs)]
+
+mod appcast;
+mod appinstaller;
+mod asdf;
+mod checksums;
+mod chocolatey;
+mod config;
+mod errors;
+mod events;
+mod github;
+mod output;
+mod redirects;
+mod release_json;
+mod rollout;
+mod targets;
+mod targets_json;
+#[cfg(test)]
+mod test_helpers;
+mod verify;
+mod versions;
+
+pub use appcast::{generate_appcast, AppcastOptions, GeneratedAppcast};
+pub use appinstaller::{generate_appinstallers, AppInstallerOptions, GeneratedAppInstallers};
+pub use asdf::generate_asdf;
+pub use checksums::{
+    backfill_checksums, fetch_release_checksums, ArchiveWithChecksums, ChecksumProgress, Checksums,
+};
+pub use chocolatey::{generate_chocolatey, ChocolateyOptions};
+pub use config::{DefaultFormats, ExtraRedirect, MuktiConfig};
+pub use errors::{NameValueParseError, ProjectCountError, RolloutError};
+pub use events::EventLog;
+pub use github::{annotate_github_release, AnnotateOptions};
+pub use redirects::{generate_redirects, GeneratedRedirects, RedirectFlavor, RedirectOptions};
+pub use release_json::{add_release, read_release_json, write_releases_json};
+pub use rollout::{abort_rollout, advance_rollout, clear_rollout, start_rollout};
+pub use targets::{Alias, Archive, TargetFormat};
+pub use targets_json::generate_targets_json;
+pub use verify::{verify_artifacts, ArtifactReport, VerifyOptions, VerifyReport};
+pub use versions::generate_versions;
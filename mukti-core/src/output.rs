@@ -0,0 +1,117 @@
+// Copyright (c) The mukti Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Helpers shared by the generators that write files to an output directory.
+
+use crate::errors::ProjectCountError;
+use atomicwrites::{AtomicFile, OverwriteBehavior};
+use camino::Utf8Path;
+use eyre::{bail, Result, WrapErr};
+use mukti_metadata::{MuktiProject, MuktiReleasesJson, ReleaseStatus, ReleaseVersionData};
+use semver::{Version, VersionReq};
+use std::io::Write as _;
+
+/// Returns the single project in the release JSON.
+pub(crate) fn single_project(
+    release_json: &MuktiReleasesJson,
+) -> Result<&MuktiProject, ProjectCountError> {
+    if release_json.projects.len() != 1 {
+        return Err(ProjectCountError {
+            count: release_json.projects.len(),
+        });
+    }
+
+    Ok(release_json
+        .projects
+        .values()
+        .next()
+        .expect("release_json has one project"))
+}
+
+/// Returns the single project in the release JSON, mutably.
+pub(crate) fn single_project_mut(
+    release_json: &mut MuktiReleasesJson,
+) -> Result<&mut MuktiProject, ProjectCountError> {
+    if release_json.projects.len() != 1 {
+        return Err(ProjectCountError {
+            count: release_json.projects.len(),
+        });
+    }
+
+    Ok(release_json
+        .projects
+        .values_mut()
+        .next()
+        .expect("release_json has one project"))
+}
+
+/// Looks up `version` in the project, or the latest active non-prerelease version if `None`.
+pub(crate) fn resolve_version<'a>(
+    project: &'a MuktiProject,
+    version: Option<&Version>,
+) -> Result<(&'a Version, &'a ReleaseVersionData)> {
+    match version {
+        Some(version) => match project.get_version_data(version) {
+            Some(data) => Ok(data),
+            None => bail!("version {} not found in releases JSON", version),
+        },
+        None => match project.get_latest_matching(&VersionReq::STAR) {
+            Some(data) => Ok(data),
+            None => bail!("no active non-prerelease versions found in releases JSON"),
+        },
+    }
+}
+
+/// Returns all active (non-yanked) versions in the project, in ascending order.
+pub(crate) fn active_versions_ascending(
+    project: &MuktiProject,
+) -> Vec<(&Version, &ReleaseVersionData)> {
+    let mut versions: Vec<_> = project
+        .all_versions()
+        .filter(|(_, version_data)| version_data.status == ReleaseStatus::Active)
+        .collect();
+    versions.sort_by_key(|(version, _)| *version);
+    versions
+}
+
+/// Returns the public (redirecting) URL for an archive, as generated by `generate-redirects`.
+pub(crate) fn public_url(base_url: &str, version: &Version, target: &str, format: &str) -> String {
+    format!(
+        "{}/{}/{}.{}",
+        base_url.trim_end_matches('/'),
+        version,
+        target,
+        format
+    )
+}
+
+/// Escapes text for use in XML attributes and character data.
+pub(crate) fn xml_escape(input: &str) -> String {
+    let mut out = String::with_capacity(input.len());
+    for c in input.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Atomically writes `contents` to `out_dir/rel_path`, creating parent directories as needed.
+pub(crate) fn write_output_file(out_dir: &Utf8Path, rel_path: &str, contents: &str) -> Result<()> {
+    let path = out_dir.join(rel_path);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .wrap_err_with(|| format!("failed to create directory {}", parent))?;
+    }
+
+    let file = AtomicFile::new(&path, OverwriteBehavior::AllowOverwrite);
+    file.write(|f| f.write_all(contents.as_bytes()))
+        .wrap_err_with(|| format!("failed to write {}", path))?;
+
+    Ok(())
+}
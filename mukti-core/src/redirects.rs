@@ -0,0 +1,925 @@
+// Copyright (c) The mukti Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use crate::{
+    config::{DefaultFormats, ExtraRedirect, MuktiConfig},
+    output::{single_project, write_output_file},
+    rollout::{rollout_edge_function, ActiveRollout, EdgePlatform},
+    targets::Alias,
+};
+use camino::{Utf8Path, Utf8PathBuf};
+use core::fmt;
+use eyre::{bail, Result};
+use mukti_metadata::{MuktiReleasesJson, ReleaseVersionData, VersionRange};
+use semver::Version;
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt::Write as _,
+};
+
+/// The flavor of `_redirects` file to generate.
+#[derive(Clone, Copy, Debug)]
+#[non_exhaustive]
+pub enum RedirectFlavor {
+    /// Netlify _redirects: purely static
+    Netlify,
+
+    /// Cloudflare _redirects: uses :version splats along with some static redirects
+    Cloudflare,
+
+    /// Netlify _redirects, plus an edge function for staged rollouts of latest
+    NetlifyEdge,
+
+    /// Cloudflare _redirects, plus a Pages Function for staged rollouts of latest
+    CloudflareEdge,
+}
+
+impl RedirectFlavor {
+    fn edge_platform(self) -> Option<EdgePlatform> {
+        match self {
+            Self::Netlify | Self::Cloudflare => None,
+            Self::NetlifyEdge => Some(EdgePlatform::Netlify),
+            Self::CloudflareEdge => Some(EdgePlatform::Cloudflare),
+        }
+    }
+}
+
+/// Options for redirect generation.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct RedirectOptions<'a> {
+    /// Additional names to redirect to archives.
+    pub aliases: &'a [Alias],
+
+    /// The flavor of `_redirects` file to generate.
+    pub flavor: RedirectFlavor,
+
+    /// The prefix for redirect paths.
+    pub prefix: &'a str,
+
+    /// Whether to rewrite `/versions` to the version list.
+    pub versions: bool,
+
+    /// The directory the platform reads functions from, required for edge flavors.
+    pub functions_dir: Option<&'a Utf8Path>,
+}
+
+impl<'a> RedirectOptions<'a> {
+    /// Creates options for the given flavor, with no aliases and the `/` prefix.
+    pub fn new(flavor: RedirectFlavor) -> Self {
+        Self {
+            aliases: &[],
+            flavor,
+            prefix: "/",
+            versions: false,
+            functions_dir: None,
+        }
+    }
+}
+
+/// The result of redirect generation.
+#[derive(Clone, Debug, Default)]
+#[non_exhaustive]
+pub struct GeneratedRedirects {
+    /// The path the staged rollout edge function was written to, for edge flavors.
+    pub edge_function: Option<Utf8PathBuf>,
+
+    /// Wildcards derived for Cloudflare flavors, along with the number of redirects each covers.
+    pub wildcards: Vec<(String, usize)>,
+
+    /// Problems that didn't prevent generation, such as rollouts that couldn't be applied.
+    pub warnings: Vec<String>,
+}
+
+/// Writes a `_redirects` file to `out_dir`.
+///
+/// If `versions` is true, `/versions` is also rewritten to the `versions/all` list written by
+/// [`generate_versions`](crate::generate_versions).
+///
+/// Edge flavors also write an edge function for staged rollouts of latest to `functions_dir`,
+/// which is required for them. This must be the directory the platform reads functions from
+/// (`netlify/edge-functions` for Netlify, `functions` for Cloudflare Pages), not the published
+/// site directory. The function handles every `latest` path, since platforms may not apply
+/// `_redirects` to paths it matches: if no rollout is in progress, it redirects all requests to
+/// the latest version.
+pub fn generate_redirects(
+    release_json: &MuktiReleasesJson,
+    config: &MuktiConfig,
+    options: &RedirectOptions<'_>,
+    out_dir: &Utf8Path,
+) -> Result<GeneratedRedirects> {
+    let project = single_project(release_json)?;
+    let mut generated = GeneratedRedirects::default();
+    let RedirectOptions {
+        aliases,
+        flavor,
+        prefix,
+        versions,
+        functions_dir,
+    } = *options;
+    let default_formats = config.default_formats.as_ref();
+
+    let edge = match (flavor.edge_platform(), functions_dir) {
+        (Some(platform), Some(functions_dir)) => Some((platform, functions_dir)),
+        (Some(_), None) => bail!("flavor {:?} requires a functions directory", flavor),
+        (None, _) => None,
+    };
+
+    let netlify_prefix = prefix.trim_end_matches('/');
+    let mut extra_redirects = resolve_extra_redirects(&config.redirects, netlify_prefix)?;
+    if versions {
+        let from = format!("{}/versions", netlify_prefix);
+        if extra_redirects.iter().any(|redirect| redirect.from == from) {
+            bail!("extra redirect from `/versions` conflicts with the version list rewrite");
+        }
+        extra_redirects.push(ExtraRedirect {
+            from,
+            to: format!("{}/versions/all", netlify_prefix),
+            status: 200,
+        });
+    }
+    let mut out = String::with_capacity(4096);
+
+    writeln!(
+        &mut out,
+        "# Generated by mukti with redirect flavor {:?}\n",
+        flavor
+    )?;
+
+    let mut redirects = Vec::new();
+    let mut latest_table = BTreeMap::new();
+    let mut edge_rollout = None;
+
+    if let Some(range) = &project.latest {
+        let latest_range_data = &project.ranges[range];
+        let mut latest_version_data = &latest_range_data.versions[&latest_range_data.latest];
+
+        if let Some(rollout) =
+            ActiveRollout::new(project, &latest_range_data.latest, &mut generated.warnings)
+        {
+            // Static redirects serve the previous version, and an edge function (if supported)
+            // serves the new version to a percentage of requests.
+            latest_version_data = rollout.previous;
+            if rollout.rollout.percentage == 0 {
+                // The rollout was aborted, so the previous version is served to all requests.
+            } else if edge.is_some() {
+                edge_rollout = Some((rollout.rollout, rollout.next));
+            } else {
+                generated.warnings.push(format!(
+                    "flavor {:?} doesn't support staged rollouts, so latest redirects will serve \
+                     {} (use an edge flavor instead)",
+                    flavor, rollout.rollout.previous
+                ));
+            }
+        }
+
+        append_redirect_list(
+            RedirectVersion::Latest,
+            latest_version_data,
+            aliases,
+            default_formats,
+            netlify_prefix,
+            &mut redirects,
+        );
+
+        if edge.is_some() {
+            let next_version_data = edge_rollout.map_or(latest_version_data, |(_, next)| next);
+            latest_table = build_latest_table(
+                next_version_data,
+                latest_version_data,
+                aliases,
+                default_formats,
+                netlify_prefix,
+            );
+        }
+    }
+
+    for (range, data) in &project.ranges {
+        if !data.is_prerelease {
+            let version_data = &data.versions[&data.latest];
+            append_redirect_list(
+                RedirectVersion::Range(*range),
+                version_data,
+                aliases,
+                default_formats,
+                netlify_prefix,
+                &mut redirects,
+            );
+        }
+        for (version, version_data) in &data.versions {
+            append_redirect_list(
+                RedirectVersion::Version(version.clone()),
+                version_data,
+                aliases,
+                default_formats,
+                netlify_prefix,
+                &mut redirects,
+            );
+        }
+    }
+
+    // Extra redirects are all static and don't overlap with generated ones, so they can go first
+    // for every flavor.
+    for redirect in &extra_redirects {
+        writeln!(out, "{}", redirect).expect("writing to a string is infallible");
+    }
+
+    match flavor {
+        RedirectFlavor::Netlify | RedirectFlavor::NetlifyEdge => {
+            // Just write out the redirect list.
+            for redirect in &redirects {
+                writeln!(out, "{}", redirect).expect("writing to a string is infallible");
+            }
+        }
+        RedirectFlavor::Cloudflare | RedirectFlavor::CloudflareEdge => {
+            // Attempt to derive wildcards from the list of redirects.
+            let wildcards = WildcardStore::build(&redirects);
+            generated.wildcards = wildcards
+                .wildcards
+                .iter()
+                .map(|wildcard| (wildcard.to_string(), wildcard.matching_redirects.len()))
+                .collect();
+
+            // First write unmatched/static redirects.
+            for redirect in &wildcards.unmatched {
+                writeln!(out, "{}", redirect).expect("writing to a string is infallible");
+            }
+
+            // Then write wildcards, since they should match less tightly than static redirects.
+            for wildcard in &wildcards.wildcards {
+                writeln!(out, "{}", wildcard).expect("writing to a string is infallible");
+            }
+        }
+    }
+
+    if let Some((platform, functions_dir)) = edge {
+        // Always write the function so that one left over from an earlier rollout is replaced.
+        let rollout = edge_rollout.map(|(rollout, _)| rollout);
+        let (path, contents) =
+            rollout_edge_function(platform, rollout, netlify_prefix, &latest_table)?;
+        write_output_file(functions_dir, &path, &contents)?;
+        generated.edge_function = Some(functions_dir.join(&path));
+    }
+
+    write_output_file(out_dir, "_redirects", &out)?;
+
+    Ok(generated)
+}
+
+/// Maps each `latest` path to its destinations for the next and previous versions of a rollout.
+///
+/// Outside of a rollout, `next` and `previous` are both the latest version.
+fn build_latest_table(
+    next: &ReleaseVersionData,
+    previous: &ReleaseVersionData,
+    aliases: &[Alias],
+    default_formats: Option<&DefaultFormats>,
+    prefix: &str,
+) -> BTreeMap<String, (Option<String>, Option<String>)> {
+    let mut table = BTreeMap::new();
+    for (data, is_next) in [(next, true), (previous, false)] {
+        let mut latest_redirects = Vec::new();
+        append_redirect_list(
+            RedirectVersion::Latest,
+            data,
+            aliases,
+            default_formats,
+            prefix,
+            &mut latest_redirects,
+        );
+        for redirect in latest_redirects {
+            let entry: &mut (Option<String>, Option<String>) =
+                table.entry(redirect.from).or_default();
+            if is_next {
+                entry.0 = Some(redirect.to);
+            } else {
+                entry.1 = Some(redirect.to);
+            }
+        }
+    }
+    table
+}
+
+/// Status codes accepted for extra redirects: rewrites and redirects supported by all flavors.
+///
+/// Cloudflare only supports rewrites (status 200) to relative paths, so those are checked for
+/// separately.
+const EXTRA_REDIRECT_STATUSES: &[u16] = &[200, 301, 302, 303, 307, 308];
+
+/// Validates extra redirects from the config, returning them with the prefix applied.
+fn resolve_extra_redirects(
+    extra_redirects: &[ExtraRedirect],
+    prefix: &str,
+) -> Result<Vec<ExtraRedirect>> {
+    let mut seen = HashSet::new();
+    let mut resolved = Vec::with_capacity(extra_redirects.len());
+
+    for redirect in extra_redirects {
+        let Some(path) = redirect.from.strip_prefix('/') else {
+            bail!(
+                "extra redirect from `{}` must start with `/`",
+                redirect.from
+            );
+        };
+        // _redirects fields are separated by whitespace.
+        if redirect.from.contains(char::is_whitespace) || redirect.to.contains(char::is_whitespace)
+        {
+            bail!(
+                "extra redirect from `{}` to `{}` must not contain whitespace",
+                redirect.from,
+                redirect.to
+            );
+        }
+        if !EXTRA_REDIRECT_STATUSES.contains(&redirect.status) {
+            bail!(
+                "extra redirect from `{}` has unsupported status {} (expected one of {:?})",
+                redirect.from,
+                redirect.status,
+                EXTRA_REDIRECT_STATUSES
+            );
+        }
+        if redirect.status == 200 && !redirect.to.starts_with('/') {
+            bail!(
+                "extra redirect from `{}` is a rewrite (status 200), so its destination `{}` \
+                 must be a relative path starting with `/`",
+                redirect.from,
+                redirect.to
+            );
+        }
+
+        // Generated redirects all start with `latest`, a version range or a version, so anything
+        // under those would conflict with current or future releases.
+        let first_segment = path.split('/').next().unwrap_or_default();
+        if first_segment == "latest"
+            || first_segment.parse::<Version>().is_ok()
+            || first_segment.parse::<VersionRange>().is_ok()
+        {
+            bail!(
+                "extra redirect from `{}` conflicts with generated redirects for `{}`",
+                redirect.from,
+                first_segment
+            );
+        }
+
+        let from = format!("{}{}", prefix, redirect.from);
+        if !seen.insert(from.clone()) {
+            bail!(
+                "extra redirect from `{}` is declared more than once",
+                redirect.from
+            );
+        }
+        resolved.push(ExtraRedirect {
+            from,
+            to: redirect.to.clone(),
+            status: redirect.status,
+        });
+    }
+
+    Ok(resolved)
+}
+
+impl fmt::Display for ExtraRedirect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} {}", self.from, self.to, self.status)
+    }
+}
+
+// In a WildcardStore, wildcards and unmatched together cover the full set of redirects
+#[derive(Debug)]
+struct WildcardStore<'a> {
+    wildcards: Vec<Wildcard<'a>>,
+    unmatched: Vec<Redirect>,
+}
+
+impl<'a> WildcardStore<'a> {
+    fn build(redirects: &'a [Redirect]) -> Self {
+        // from_components -> ((kind, to_components) -> list of redirects)
+        let mut url_matches: HashMap<_, HashMap<_, Vec<_>>> = HashMap::new();
+        let mut unmatched = Vec::new();
+
+        for redirect in redirects {
+            // Only consider full versions.
+            if !matches!(redirect.version, RedirectVersion::Version(_)) {
+                unmatched.push(redirect.clone());
+                continue;
+            }
+
+            let version_str = redirect.version.to_string();
+            let (from_start, from_end) = match redirect.from.split_once(&version_str) {
+                Some((start, end)) => (start, end),
+                None => {
+                    unmatched.push(redirect.clone());
+                    continue;
+                }
+            };
+
+            let to_components: Vec<_> = redirect.to.split(&version_str).collect();
+
+            url_matches
+                .entry((from_start, from_end))
+                .or_default()
+                .entry((redirect.kind, to_components))
+                .or_default()
+                .push(redirect);
+        }
+
+        // For each from key, look through all the to keys and find the most common one.
+        let mut wildcards = Vec::new();
+
+        for ((from_start, from_end), mut to_maps) in url_matches {
+            // (kind, to_components, redirects)
+            let mut best_to: Option<(RedirectKind, &[_], &[_])> = None;
+
+            for ((kind, to_components), redirects) in &to_maps {
+                if let Some((_, _, best_redirects)) = &best_to {
+                    if redirects.len() > best_redirects.len() {
+                        best_to = Some((*kind, to_components, redirects));
+                    }
+                } else {
+                    best_to = Some((*kind, to_components, redirects));
+                }
+            }
+
+            if let Some((kind, to_components, best_redirects)) = best_to {
+                let wildcard = Wildcard {
+                    kind,
+                    from_components: (from_start, from_end),
+                    to_components: to_components.to_vec(),
+                    matching_redirects: best_redirects.to_vec(),
+                };
+                wildcards.push(wildcard);
+
+                // Everything here is covered by the wildcard. (to_vec is required to avoid
+                // borrowing issues.)
+                let ktc = (kind, to_components.to_vec());
+                to_maps.remove(&ktc);
+            }
+
+            // Anything left goes into unmatched.
+            for (_, redirects) in to_maps {
+                unmatched.extend(redirects.into_iter().cloned());
+            }
+        }
+
+        // Sort the wildcard and unmatched lists.
+        wildcards.sort_unstable_by_key(|wildcard| (wildcard.kind, wildcard.from_components));
+        unmatched.sort();
+
+        Self {
+            wildcards,
+            unmatched,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Wildcard<'a> {
+    // The version can only show up once in the redirect "from", therefore two components
+    kind: RedirectKind,
+    from_components: (&'a str, &'a str),
+    to_components: Vec<&'a str>,
+    matching_redirects: Vec<&'a Redirect>,
+}
+
+impl Wildcard<'_> {
+    const VERSION_PLACEHOLDER: &'static str = ":version";
+}
+
+impl fmt::Display for Wildcard<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (from_start, from_end) = self.from_components;
+        let to = self.to_components.join(Self::VERSION_PLACEHOLDER);
+
+        write!(
+            f,
+            "{from_start}{}{from_end} {to} 302",
+            Self::VERSION_PLACEHOLDER,
+        )
+    }
+}
+
+fn append_redirect_list(
+    version: RedirectVersion,
+    version_data: &ReleaseVersionData,
+    aliases: &[Alias],
+    default_formats: Option<&DefaultFormats>,
+    prefix: &str,
+    out: &mut Vec<Redirect>,
+) {
+    out.push(Redirect {
+        version: version.clone(),
+        kind: RedirectKind::Release,
+        from: format!("{}/{}/release", prefix, version),
+        to: version_data.release_url.clone(),
+        code: 302,
+    });
+
+    for location in &version_data.locations {
+        out.push(Redirect {
+            version: version.clone(),
+            kind: RedirectKind::Location,
+            from: format!(
+                "{}/{}/{}.{}",
+                prefix, version, location.target, location.format
+            ),
+            to: location.url.clone(),
+            code: 302,
+        });
+        for alias in aliases.iter().filter(|alias| {
+            alias.target_format.target == location.target
+                && alias.target_format.format == location.format
+        }) {
+            out.push(Redirect {
+                version: version.clone(),
+                kind: RedirectKind::Alias,
+                from: format!("{}/{}/{}", prefix, version, alias.alias),
+                to: location.url.clone(),
+                code: 302,
+            });
+        }
+    }
+
+    // Redirect extension-less paths to the preferred format for each target, if configured.
+    // Aliases with the same name as a target take precedence.
+    let Some(default_formats) = default_formats else {
+        return;
+    };
+    let mut seen_targets = HashSet::new();
+    for location in &version_data.locations {
+        let target = location.target.as_str();
+        if !seen_targets.insert(target) || aliases.iter().any(|alias| alias.alias == target) {
+            continue;
+        }
+
+        let preferred = default_formats
+            .for_target(target)
+            .iter()
+            .find_map(|format| {
+                version_data
+                    .locations
+                    .iter()
+                    .find(|location| location.target == target && &location.format == format)
+            });
+        if let Some(preferred) = preferred {
+            out.push(Redirect {
+                version: version.clone(),
+                kind: RedirectKind::DefaultFormat,
+                from: format!("{}/{}/{}", prefix, version, target),
+                to: preferred.url.clone(),
+                code: 302,
+            });
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, PartialOrd, Ord, Hash)]
+struct Redirect {
+    version: RedirectVersion,
+    kind: RedirectKind,
+    from: String,
+    to: String,
+    code: u16,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, PartialOrd, Ord, Hash)]
+enum RedirectKind {
+    // Order here determines sort order for `Redirect`.
+    Release,
+    Location,
+    Alias,
+    DefaultFormat,
+}
+
+impl fmt::Display for Redirect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} {}", self.from, self.to, self.code)
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, PartialOrd, Ord, Hash)]
+enum RedirectVersion {
+    Latest,
+    Range(VersionRange),
+    Version(Version),
+}
+
+impl fmt::Display for RedirectVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Latest => write!(f, "latest"),
+            Self::Range(range) => write!(f, "{}", range),
+            Self::Version(version) => write!(f, "{}", version),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::{fixture_json, version_data_mut};
+
+    fn fixture_version_data() -> ReleaseVersionData {
+        version_data_mut(&mut fixture_json(), &Version::new(0, 5, 3)).clone()
+    }
+
+    fn default_format_redirects(default_formats: Option<&DefaultFormats>) -> Vec<(String, String)> {
+        let mut redirects = Vec::new();
+        append_redirect_list(
+            RedirectVersion::Version(Version::new(0, 5, 3)),
+            &fixture_version_data(),
+            &[],
+            default_formats,
+            "",
+            &mut redirects,
+        );
+        redirects
+            .into_iter()
+            .filter(|redirect| redirect.kind == RedirectKind::DefaultFormat)
+            .map(|redirect| (redirect.from, redirect.to))
+            .collect()
+    }
+
+    #[test]
+    fn test_default_format_redirects() {
+        let version_data = fixture_version_data();
+        let url = |target: &str, format: &str| {
+            version_data
+                .locations
+                .iter()
+                .find(|location| location.target == target && location.format == format)
+                .unwrap()
+                .url
+                .clone()
+        };
+        let expected = |windows_format: &str| {
+            vec![
+                (
+                    "/0.5.3/x86_64-unknown-linux-gnu".to_owned(),
+                    url("x86_64-unknown-linux-gnu", "tar.gz"),
+                ),
+                (
+                    "/0.5.3/x86_64-pc-windows-msvc".to_owned(),
+                    url("x86_64-pc-windows-msvc", windows_format),
+                ),
+            ]
+        };
+
+        assert_eq!(
+            default_format_redirects(None),
+            [],
+            "no redirects are generated unless configured"
+        );
+        assert_eq!(
+            default_format_redirects(Some(&DefaultFormats::default())),
+            expected("zip"),
+            "Windows targets use the Windows list, so tar.gz isn't considered for them"
+        );
+
+        let tar_gz_first = DefaultFormats {
+            windows: vec!["tar.gz".to_owned(), "zip".to_owned()],
+            ..Default::default()
+        };
+        assert_eq!(
+            default_format_redirects(Some(&tar_gz_first)),
+            expected("tar.gz"),
+            "the first listed format wins when a target has several"
+        );
+
+        let msi_first = DefaultFormats {
+            windows: vec!["msi".to_owned(), "zip".to_owned()],
+            ..Default::default()
+        };
+        assert_eq!(
+            default_format_redirects(Some(&msi_first)),
+            expected("zip"),
+            "formats without an artifact are skipped"
+        );
+    }
+
+    #[test]
+    fn test_rollout_table() {
+        let json = fixture_json();
+        let mut project = json.projects["mukti"].clone();
+        project.rollout = Some(mukti_metadata::Rollout {
+            version: Version::new(0, 5, 3),
+            previous: Version::new(0, 5, 1),
+            percentage: 10,
+        });
+        let rollout =
+            ActiveRollout::new(&project, &Version::new(0, 5, 3), &mut Vec::new()).unwrap();
+
+        let table = build_latest_table(rollout.next, rollout.previous, &[], None, "/mukti");
+        let url = |version: &str, target: &str, format: &str| {
+            let (_, data) = project.get_version_data(&version.parse().unwrap()).unwrap();
+            data.locations
+                .iter()
+                .find(|location| location.target == target && location.format == format)
+                .map(|location| location.url.clone())
+        };
+
+        assert_eq!(
+            table.keys().collect::<Vec<_>>(),
+            [
+                "/mukti/latest/release",
+                "/mukti/latest/x86_64-pc-windows-msvc.tar.gz",
+                "/mukti/latest/x86_64-pc-windows-msvc.zip",
+                "/mukti/latest/x86_64-unknown-linux-gnu.tar.gz",
+            ],
+        );
+        assert_eq!(
+            table["/mukti/latest/x86_64-unknown-linux-gnu.tar.gz"],
+            (
+                url("0.5.3", "x86_64-unknown-linux-gnu", "tar.gz"),
+                url("0.5.1", "x86_64-unknown-linux-gnu", "tar.gz"),
+            ),
+            "each path is paired with the next and previous versions' destinations"
+        );
+        assert_eq!(
+            table["/mukti/latest/release"],
+            (
+                Some(rollout.next.release_url.clone()),
+                Some(rollout.previous.release_url.clone()),
+            ),
+        );
+    }
+
+    fn extra(from: &str, status: u16) -> ExtraRedirect {
+        extra_to(from, "https://example.com", status)
+    }
+
+    fn extra_to(from: &str, to: &str, status: u16) -> ExtraRedirect {
+        ExtraRedirect {
+            from: from.to_owned(),
+            to: to.to_owned(),
+            status,
+        }
+    }
+
+    #[test]
+    fn test_resolve_extra_redirects() {
+        let resolved = resolve_extra_redirects(
+            &[
+                extra("/install", 302),
+                extra("/docs", 301),
+                extra_to("/help", "/docs/help", 200),
+            ],
+            "/mukti",
+        )
+        .unwrap();
+        let lines: Vec<_> = resolved.iter().map(|r| r.to_string()).collect();
+        assert_eq!(
+            lines,
+            [
+                "/mukti/install https://example.com 302",
+                "/mukti/docs https://example.com 301",
+                "/mukti/help /docs/help 200",
+            ],
+            "prefix is applied"
+        );
+
+        for (redirect, reason) in [
+            (extra("install", 302), "missing leading slash"),
+            (extra("/install", 404), "unsupported status"),
+            (extra("/latest/install", 302), "conflicts with latest"),
+            (extra("/0.5/install", 302), "conflicts with range"),
+            (extra("/1", 302), "conflicts with major range"),
+            (extra("/0.5.3/install", 302), "conflicts with version"),
+            (extra("/in stall", 302), "whitespace in from"),
+            (extra_to("/install", "a b", 302), "whitespace in to"),
+            (extra_to("/install", "/a\tb", 200), "tab in to"),
+            (extra("/install", 200), "rewrite to an absolute URL"),
+        ] {
+            assert!(
+                resolve_extra_redirects(&[redirect], "").is_err(),
+                "{reason}"
+            );
+        }
+
+        assert!(
+            resolve_extra_redirects(&[extra("/install", 302), extra("/install", 301)], "").is_err(),
+            "duplicate redirects"
+        );
+    }
+
+    fn generate_fixture_redirects(
+        release_json: &MuktiReleasesJson,
+        flavor: RedirectFlavor,
+    ) -> (GeneratedRedirects, String, Utf8PathBuf, tempfile::TempDir) {
+        let dir = tempfile::tempdir().unwrap();
+        let dir_path = Utf8Path::from_path(dir.path()).unwrap();
+        let out_dir = dir_path.join("out");
+        let functions_dir = dir_path.join("functions");
+
+        let mut options = RedirectOptions::new(flavor);
+        options.versions = true;
+        options.functions_dir = Some(&functions_dir);
+        let generated =
+            generate_redirects(release_json, &MuktiConfig::default(), &options, &out_dir).unwrap();
+        let contents = std::fs::read_to_string(out_dir.join("_redirects")).unwrap();
+
+        (generated, contents, functions_dir, dir)
+    }
+
+    #[test]
+    fn test_generate_redirects() {
+        let json = fixture_json();
+        let (generated, contents, _, _dir) =
+            generate_fixture_redirects(&json, RedirectFlavor::Netlify);
+        let lines: Vec<_> = contents.lines().collect();
+
+        assert_eq!(
+            generated.edge_function, None,
+            "static flavors have no edge function"
+        );
+        assert_eq!(generated.warnings, Vec::<String>::new());
+        for line in [
+            "/versions /versions/all 200",
+            "/latest/release https://my-release-url/version-0.5.3 302",
+            "/0.5/release https://my-release-url/version-0.5.3 302",
+            "/0.5.2/release https://my-release-url/version-0.5.2 302",
+            "/0.6.0-alpha.1/release https://my-release-url/version-0.6.0-alpha.1 302",
+        ] {
+            assert!(lines.contains(&line), "{line} is in:\n{contents}");
+        }
+        assert!(
+            !lines.iter().any(|line| line.starts_with("/0.6/")),
+            "pre-release-only ranges aren't redirected"
+        );
+    }
+
+    #[test]
+    fn test_generate_redirects_rollout() {
+        let mut json = fixture_json();
+        crate::start_rollout(&mut json, Version::new(0, 5, 3), 10).unwrap();
+        let (generated, contents, functions_dir, _dir) =
+            generate_fixture_redirects(&json, RedirectFlavor::NetlifyEdge);
+
+        let edge_function = generated.edge_function.expect("edge function was written");
+        assert!(edge_function.starts_with(&functions_dir));
+        let function = std::fs::read_to_string(&edge_function).unwrap();
+        assert!(
+            function.contains("https://my-release-url/version-0.5.3"),
+            "edge function serves the new version:\n{function}"
+        );
+        assert!(
+            contents
+                .lines()
+                .any(|line| line.starts_with("/latest/release ") && line.contains("0.5.1")),
+            "static redirects serve the previous version:\n{contents}"
+        );
+    }
+
+    #[test]
+    fn test_generate_redirects_edge_without_rollout() {
+        let json = fixture_json();
+        let (generated, _, functions_dir, _dir) =
+            generate_fixture_redirects(&json, RedirectFlavor::CloudflareEdge);
+
+        let edge_function = generated.edge_function.expect("edge function was written");
+        assert_eq!(edge_function, functions_dir.join("latest/[[path]].js"));
+        let function = std::fs::read_to_string(&edge_function).unwrap();
+        assert!(function.contains("const PERCENTAGE = 0;"), "{function}");
+
+        // Cloudflare doesn't apply _redirects to paths a Pages Function matches, so the function
+        // must redirect every latest path itself.
+        let table = function
+            .split_once("const REDIRECTS = ")
+            .and_then(|(_, rest)| rest.split_once(";\n"))
+            .map(|(table, _)| table)
+            .unwrap();
+        let table: serde_json::Value = serde_json::from_str(table).unwrap();
+        let release_url = "https://my-release-url/version-0.5.3";
+        assert_eq!(
+            table["/latest/release"],
+            serde_json::json!({ "next": release_url, "previous": release_url }),
+        );
+        assert_eq!(
+            table.as_object().unwrap().len(),
+            4,
+            "every latest path is in the table:\n{function}"
+        );
+    }
+
+    #[test]
+    fn test_generate_redirects_yanked_aborted_rollout() {
+        let mut json = fixture_json();
+        let v0_5_3 = Version::new(0, 5, 3);
+        crate::start_rollout(&mut json, v0_5_3.clone(), 10).unwrap();
+        crate::abort_rollout(&mut json).unwrap();
+        version_data_mut(&mut json, &v0_5_3).status = mukti_metadata::ReleaseStatus::Yanked;
+        assert!(
+            crate::clear_rollout(&mut json).is_err(),
+            "yanked 0.5.3 is still served by latest redirects"
+        );
+
+        let (generated, contents, _, _dir) =
+            generate_fixture_redirects(&json, RedirectFlavor::Netlify);
+        assert_eq!(generated.warnings, Vec::<String>::new());
+        assert!(
+            contents
+                .lines()
+                .any(|line| line == "/latest/release https://github.com/nextest-rs/mukti/releases/mukti-bin-0.5.1 302"),
+            "the aborted rollout keeps serving the previous version:\n{contents}"
+        );
+    }
+}
@@ -3,24 +3,27 @@
 
 //! Add and update to release JSON.
 
-use crate::checksums::ArchiveWithChecksums;
+use crate::{
+    checksums::ArchiveWithChecksums, errors::ProjectCountError, output::single_project_mut,
+};
 use atomicwrites::{AtomicFile, OverwriteBehavior};
 use camino::Utf8Path;
-use color_eyre::eyre::{bail, Result, WrapErr};
+use eyre::{bail, Result, WrapErr};
 use mukti_metadata::{
-    MuktiReleasesJson, ReleaseLocation, ReleaseRangeData, ReleaseStatus, ReleaseVersionData,
-    VersionRange,
+    MuktiReleasesJson, ReleaseEventKind, ReleaseLocation, ReleaseRangeData, ReleaseStatus,
+    ReleaseVersionData, VersionRange,
 };
 use semver::Version;
 use std::{collections::BTreeMap, io::BufWriter};
 
 /// Read the releases.json file.
-pub(crate) fn read_release_json(path: &Utf8Path, allow_missing: bool) -> Result<MuktiReleasesJson> {
-    let release_json: MuktiReleasesJson = if path.exists() {
-        let json = std::fs::read_to_string(path)
-            .wrap_err_with(|| format!("failed to read releases JSON file at {}", path))?;
-        serde_json::from_str(&json)
-            .wrap_err_with(|| format!("failed to deserialize releases JSON at {}", path))?
+///
+/// This doesn't check that the file is internally consistent: use
+/// [`MuktiReleasesJson::validate`] for that.
+pub fn read_release_json(path: &Utf8Path, allow_missing: bool) -> Result<MuktiReleasesJson> {
+    let release_json = if path.exists() {
+        MuktiReleasesJson::from_path(path)
+            .wrap_err_with(|| format!("failed to read releases JSON at {}", path))?
     } else if allow_missing {
         MuktiReleasesJson::default()
     } else {
@@ -30,34 +33,25 @@ pub(crate) fn read_release_json(path: &Utf8Path, allow_missing: bool) -> Result<
     Ok(release_json)
 }
 
-pub(crate) fn update_release_json(
+/// Adds a release to the releases JSON, updating the latest versions.
+///
+/// Returns the event to record, or `None` if there are no archives to add.
+pub fn add_release(
     release_json: &mut MuktiReleasesJson,
     release_url: &str,
     version: &Version,
     archives: Vec<ArchiveWithChecksums>,
-    path: &Utf8Path,
-) -> Result<()> {
+) -> Result<Option<ReleaseEventKind>, ProjectCountError> {
     if archives.is_empty() {
         // No archives to add -- skip this.
-        return Ok(());
+        return Ok(None);
     }
 
-    if release_json.projects.len() != 1 {
-        bail!(
-            "mukti-bin currently only supports one project, {} found",
-            release_json.projects.len()
-        );
-    }
-
-    let project = release_json
-        .projects
-        .values_mut()
-        .next()
-        .expect("release_json has one project");
+    let project = single_project_mut(release_json)?;
 
     // Read the release JSON file.
     let range = VersionRange::from_version(version);
-    {
+    let location_urls = {
         let data = project
             .ranges
             .entry(range)
@@ -70,15 +64,10 @@ pub(crate) fn update_release_json(
         let locations: Vec<_> = archives
             .into_iter()
             .map(|archive| {
+                // Archives whose checksums couldn't be fetched are recorded without them.
                 let checksums = match archive.checksums {
                     Ok(checksums) => checksums.to_checksum_map(),
-                    Err(e) => {
-                        eprintln!(
-                            "failed to compute checksums for {}: {}",
-                            archive.archive.name, e
-                        );
-                        BTreeMap::new()
-                    }
+                    Err(_) => BTreeMap::new(),
                 };
 
                 ReleaseLocation {
@@ -89,6 +78,10 @@ pub(crate) fn update_release_json(
                 }
             })
             .collect();
+        let location_urls = locations
+            .iter()
+            .map(|location| location.url.clone())
+            .collect();
         data.versions.insert(
             version.clone(),
             ReleaseVersionData {
@@ -121,7 +114,9 @@ pub(crate) fn update_release_json(
                 data.is_prerelease = true;
             }
         }
-    }
+
+        location_urls
+    };
 
     // Check if there's a newer release.
     let latest_range = project
@@ -131,12 +126,15 @@ pub(crate) fn update_release_json(
         .max();
     project.latest = latest_range;
 
-    write_releases_json(release_json, path)?;
-
-    Ok(())
+    Ok(Some(ReleaseEventKind::ReleaseAdded {
+        version: version.clone(),
+        release_url: release_url.to_owned(),
+        location_urls,
+    }))
 }
 
-pub(crate) fn write_releases_json(release_json: &MuktiReleasesJson, path: &Utf8Path) -> Result<()> {
+/// Atomically writes the releases JSON to `path`.
+pub fn write_releases_json(release_json: &MuktiReleasesJson, path: &Utf8Path) -> Result<()> {
     let file = AtomicFile::new(path, OverwriteBehavior::AllowOverwrite);
     file.write(|f| serde_json::to_writer_pretty(BufWriter::new(f), &release_json))
         .wrap_err_with(|| format!("failed to serialize releases JSON to {}", path))?;
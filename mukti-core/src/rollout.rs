@@ -0,0 +1,484 @@
+// Copyright (c) The mukti Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Staged rollouts of new versions to `latest`.
+
+use crate::{errors::RolloutError, output::single_project_mut};
+use eyre::Result;
+use mukti_metadata::{
+    MuktiProject, MuktiReleasesJson, ReleaseEventKind, ReleaseStatus, ReleaseVersionData, Rollout,
+};
+use semver::Version;
+use std::{collections::BTreeMap, fmt::Write as _};
+
+/// Starts a staged rollout of `version`, which must be the latest version.
+///
+/// Requests not included in the rollout are served the latest active version before `version`.
+pub fn start_rollout(
+    release_json: &mut MuktiReleasesJson,
+    version: Version,
+    percentage: u8,
+) -> Result<ReleaseEventKind, RolloutError> {
+    let project = single_project_mut(release_json)?;
+    if !(1..=99).contains(&percentage) {
+        return Err(RolloutError::InvalidPercentage {
+            percentage,
+            max: 99,
+        });
+    }
+    if let Some(rollout) = &project.rollout {
+        if rollout.percentage > 0 {
+            return Err(RolloutError::InProgress {
+                version: rollout.version.clone(),
+            });
+        }
+    }
+    match redirect_latest(project) {
+        Some(latest) if latest == &version => {}
+        Some(latest) => {
+            return Err(RolloutError::NotLatest {
+                version,
+                latest: latest.clone(),
+            });
+        }
+        None => return Err(RolloutError::VersionNotFound { version }),
+    }
+    let Some(previous) = project
+        .all_versions()
+        .find(|(v, data)| *v < &version && v.pre.is_empty() && data.status == ReleaseStatus::Active)
+        .map(|(v, _)| v.clone())
+    else {
+        return Err(RolloutError::NoPreviousVersion { version });
+    };
+
+    project.rollout = Some(Rollout {
+        version: version.clone(),
+        previous: previous.clone(),
+        percentage,
+    });
+
+    Ok(ReleaseEventKind::RolloutStarted {
+        version,
+        previous,
+        percentage,
+    })
+}
+
+/// Changes the percentage of the rollout in progress, completing it at 100.
+pub fn advance_rollout(
+    release_json: &mut MuktiReleasesJson,
+    percentage: u8,
+) -> Result<ReleaseEventKind, RolloutError> {
+    let project = single_project_mut(release_json)?;
+    if !(1..=100).contains(&percentage) {
+        return Err(RolloutError::InvalidPercentage {
+            percentage,
+            max: 100,
+        });
+    }
+    let rollout = project.rollout.as_mut().ok_or(RolloutError::NoRollout)?;
+    if rollout.percentage == 0 {
+        return Err(RolloutError::Aborted {
+            version: rollout.version.clone(),
+        });
+    }
+    let version = rollout.version.clone();
+    if percentage == 100 {
+        project.rollout = None;
+    } else {
+        rollout.percentage = percentage;
+    }
+
+    Ok(ReleaseEventKind::RolloutAdvanced {
+        version,
+        percentage,
+    })
+}
+
+/// Aborts the rollout in progress, serving the previous version to all requests.
+pub fn abort_rollout(
+    release_json: &mut MuktiReleasesJson,
+) -> Result<ReleaseEventKind, RolloutError> {
+    let project = single_project_mut(release_json)?;
+    let rollout = project.rollout.as_mut().ok_or(RolloutError::NoRollout)?;
+    if rollout.percentage == 0 {
+        return Err(RolloutError::Aborted {
+            version: rollout.version.clone(),
+        });
+    }
+    rollout.percentage = 0;
+
+    Ok(ReleaseEventKind::RolloutAborted {
+        version: rollout.version.clone(),
+    })
+}
+
+/// Removes a rollout that was aborted or superseded by a newer latest version.
+///
+/// An aborted rollout of the current latest version can't be cleared, since that would serve it to
+/// all requests: release a newer version first, or start the rollout again and complete it.
+/// Yanking the version doesn't help, since `latest` redirects still serve yanked versions.
+pub fn clear_rollout(
+    release_json: &mut MuktiReleasesJson,
+) -> Result<ReleaseEventKind, RolloutError> {
+    let project = single_project_mut(release_json)?;
+    let rollout = project.rollout.as_ref().ok_or(RolloutError::NoRollout)?;
+    let is_latest = redirect_latest(project) == Some(&rollout.version);
+    if is_latest {
+        let version = rollout.version.clone();
+        if rollout.percentage > 0 {
+            return Err(RolloutError::InProgress { version });
+        }
+        return Err(RolloutError::LatestAborted { version });
+    }
+
+    let version = rollout.version.clone();
+    project.rollout = None;
+
+    Ok(ReleaseEventKind::RolloutCleared { version })
+}
+
+/// Returns the version served by `latest` redirects: the latest version of the latest range.
+///
+/// Unlike [`MuktiProject::get_latest_matching`], this includes yanked versions, matching what
+/// [`generate_redirects`](crate::generate_redirects) serves.
+pub(crate) fn redirect_latest(project: &MuktiProject) -> Option<&Version> {
+    let range = project.latest.as_ref()?;
+    project.ranges.get(range).map(|data| &data.latest)
+}
+
+/// A rollout that applies to the current latest version.
+#[derive(Debug)]
+pub(crate) struct ActiveRollout<'a> {
+    pub(crate) rollout: &'a Rollout,
+    pub(crate) next: &'a ReleaseVersionData,
+    pub(crate) previous: &'a ReleaseVersionData,
+}
+
+impl<'a> ActiveRollout<'a> {
+    /// Returns the rollout for the project, if it applies to the latest version.
+    ///
+    /// Rollouts that have been superseded by a newer release are ignored, with a warning.
+    pub(crate) fn new(
+        project: &'a MuktiProject,
+        latest: &Version,
+        warnings: &mut Vec<String>,
+    ) -> Option<Self> {
+        let rollout = project.rollout.as_ref()?;
+        if &rollout.version != latest {
+            warnings.push(format!(
+                "ignoring rollout of {}, since the latest version is {}",
+                rollout.version, latest
+            ));
+            return None;
+        }
+
+        let (_, next) = project.get_version_data(&rollout.version)?;
+        let Some((_, previous)) = project.get_version_data(&rollout.previous) else {
+            warnings.push(format!(
+                "ignoring rollout of {}, since previous version {} was not found",
+                rollout.version, rollout.previous
+            ));
+            return None;
+        };
+
+        Some(Self {
+            rollout,
+            next,
+            previous,
+        })
+    }
+}
+
+/// The platform to generate a rollout edge function for.
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum EdgePlatform {
+    Netlify,
+    Cloudflare,
+}
+
+/// Generates an edge function that serves the new version to a percentage of requests.
+///
+/// `redirects` maps each `latest` path to its (next, previous) destinations. If `rollout` is
+/// `None`, every request is redirected to the previous destination. Returns the path of the
+/// function relative to the platform's functions directory, along with its contents.
+pub(crate) fn rollout_edge_function(
+    platform: EdgePlatform,
+    rollout: Option<&Rollout>,
+    prefix: &str,
+    redirects: &BTreeMap<String, (Option<String>, Option<String>)>,
+) -> Result<(String, String)> {
+    let table: BTreeMap<_, _> = redirects
+        .iter()
+        .map(|(from, (next, previous))| {
+            // If a path only exists for one of the versions, always redirect to it.
+            let next = next.as_ref().or(previous.as_ref());
+            let previous = previous.as_ref().or(next);
+            (
+                from,
+                serde_json::json!({ "next": next, "previous": previous }),
+            )
+        })
+        .collect();
+
+    let mut out = String::with_capacity(4096);
+    let percentage = match rollout {
+        Some(rollout) => {
+            writeln!(
+                out,
+                "// Generated by mukti: staged rollout of {} at {}% (previous: {})",
+                rollout.version, rollout.percentage, rollout.previous
+            )?;
+            rollout.percentage
+        }
+        None => {
+            writeln!(out, "// Generated by mukti: no staged rollout in progress")?;
+            0
+        }
+    };
+    writeln!(out, "const PERCENTAGE = {};", percentage)?;
+    writeln!(
+        out,
+        "const REDIRECTS = {};",
+        serde_json::to_string_pretty(&table)?
+    )?;
+    writeln!(out)?;
+    writeln!(out, "function rolloutRedirect(request) {{")?;
+    writeln!(
+        out,
+        "  const redirect = REDIRECTS[new URL(request.url).pathname];"
+    )?;
+    writeln!(out, "  if (!redirect) {{")?;
+    writeln!(out, "    return null;")?;
+    writeln!(out, "  }}")?;
+    writeln!(
+        out,
+        "  const location = Math.random() * 100 < PERCENTAGE ? redirect.next : redirect.previous;"
+    )?;
+    writeln!(out, "  return Response.redirect(location, 302);")?;
+    writeln!(out, "}}")?;
+    writeln!(out)?;
+
+    let path = match platform {
+        EdgePlatform::Netlify => {
+            writeln!(out, "export default async (request, context) => {{")?;
+            writeln!(out, "  return rolloutRedirect(request) ?? context.next();")?;
+            writeln!(out, "}};")?;
+            writeln!(out)?;
+            writeln!(
+                out,
+                "export const config = {{ path: \"{}/latest/*\" }};",
+                prefix
+            )?;
+            "mukti-rollout.js".to_owned()
+        }
+        EdgePlatform::Cloudflare => {
+            writeln!(out, "export async function onRequest(context) {{")?;
+            writeln!(
+                out,
+                "  return rolloutRedirect(context.request) ?? context.next();"
+            )?;
+            writeln!(out, "}}")?;
+            format!("{}/latest/[[path]].js", prefix)
+                .trim_start_matches('/')
+                .to_owned()
+        }
+    };
+
+    Ok((path, out))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        test_helpers::{fixture_json, version_data_mut},
+        ArchiveWithChecksums,
+    };
+
+    fn yank(json: &mut MuktiReleasesJson, version: &Version) {
+        version_data_mut(json, version).status = ReleaseStatus::Yanked;
+    }
+
+    #[test]
+    fn test_start_rollout() {
+        let v0_5_1 = Version::new(0, 5, 1);
+        let v0_5_3 = Version::new(0, 5, 3);
+
+        let mut json = fixture_json();
+        for percentage in [0, 100] {
+            assert!(
+                matches!(
+                    start_rollout(&mut json, v0_5_3.clone(), percentage),
+                    Err(RolloutError::InvalidPercentage { max: 99, .. })
+                ),
+                "percentage {percentage} is out of range"
+            );
+        }
+        assert!(
+            matches!(
+                start_rollout(&mut json, v0_5_1.clone(), 10),
+                Err(RolloutError::NotLatest { latest, .. }) if latest == v0_5_3
+            ),
+            "only the latest version can be rolled out"
+        );
+
+        start_rollout(&mut json, v0_5_3.clone(), 10).unwrap();
+        let rollout = json.projects["mukti"].rollout.as_ref().unwrap();
+        assert_eq!(
+            rollout.previous, v0_5_1,
+            "previous skips the yanked 0.5.2 and the 0.6 pre-release"
+        );
+        assert!(
+            matches!(
+                start_rollout(&mut json, v0_5_3.clone(), 20),
+                Err(RolloutError::InProgress { .. })
+            ),
+            "a rollout is already in progress"
+        );
+
+        abort_rollout(&mut json).unwrap();
+        start_rollout(&mut json, v0_5_3.clone(), 20).expect("aborted rollouts can be restarted");
+
+        let mut json = fixture_json();
+        yank(&mut json, &v0_5_1);
+        assert!(
+            matches!(
+                start_rollout(&mut json, v0_5_3, 10),
+                Err(RolloutError::NoPreviousVersion { .. })
+            ),
+            "no earlier active version to roll out from"
+        );
+    }
+
+    #[test]
+    fn test_advance_abort_clear() {
+        let v0_5_3 = Version::new(0, 5, 3);
+
+        let mut json = fixture_json();
+        assert!(
+            matches!(advance_rollout(&mut json, 50), Err(RolloutError::NoRollout)),
+            "no rollout is in progress"
+        );
+        start_rollout(&mut json, v0_5_3.clone(), 10).unwrap();
+        advance_rollout(&mut json, 50).unwrap();
+        assert_eq!(
+            json.projects["mukti"].rollout.as_ref().unwrap().percentage,
+            50
+        );
+        assert!(
+            matches!(
+                clear_rollout(&mut json),
+                Err(RolloutError::InProgress { .. })
+            ),
+            "rollouts in progress can't be cleared"
+        );
+        advance_rollout(&mut json, 100).unwrap();
+        assert!(
+            json.projects["mukti"].rollout.is_none(),
+            "advancing to 100 completes the rollout"
+        );
+
+        start_rollout(&mut json, v0_5_3.clone(), 10).unwrap();
+        abort_rollout(&mut json).unwrap();
+        assert_eq!(
+            json.projects["mukti"].rollout.as_ref().unwrap().percentage,
+            0
+        );
+        assert!(
+            matches!(
+                advance_rollout(&mut json, 50),
+                Err(RolloutError::Aborted { .. })
+            ),
+            "aborted rollouts can't be advanced"
+        );
+        assert!(
+            matches!(abort_rollout(&mut json), Err(RolloutError::Aborted { .. })),
+            "aborted rollouts can't be aborted again"
+        );
+        assert!(
+            matches!(
+                clear_rollout(&mut json),
+                Err(RolloutError::LatestAborted { .. })
+            ),
+            "aborted rollouts of the latest version can't be cleared"
+        );
+
+        yank(&mut json, &v0_5_3);
+        assert!(
+            matches!(
+                clear_rollout(&mut json),
+                Err(RolloutError::LatestAborted { .. })
+            ),
+            "latest redirects still serve yanked versions, so yanking doesn't allow clearing"
+        );
+
+        let archive = ArchiveWithChecksums {
+            archive: "x86_64-unknown-linux-gnu:tar.gz=mukti.tar.gz"
+                .parse()
+                .unwrap(),
+            url: "https://example.com/mukti.tar.gz".to_owned(),
+            checksums: Err(eyre::eyre!("not fetched")),
+        };
+        crate::add_release(
+            &mut json,
+            "https://my-release-url/version-0.5.4",
+            &Version::new(0, 5, 4),
+            vec![archive],
+        )
+        .unwrap();
+        clear_rollout(&mut json).expect("superseded rollouts can be cleared");
+        assert!(json.projects["mukti"].rollout.is_none());
+    }
+
+    #[test]
+    fn test_rollout_edge_function_table() {
+        let rollout = Rollout {
+            version: Version::new(0, 5, 3),
+            previous: Version::new(0, 5, 1),
+            percentage: 10,
+        };
+        let redirects: BTreeMap<_, _> = [
+            ("/latest/both", Some("next-both"), Some("previous-both")),
+            ("/latest/next-only", Some("next-only"), None),
+            ("/latest/previous-only", None, Some("previous-only")),
+        ]
+        .into_iter()
+        .map(|(from, next, previous)| {
+            (
+                from.to_owned(),
+                (next.map(str::to_owned), previous.map(str::to_owned)),
+            )
+        })
+        .collect();
+
+        let (path, contents) =
+            rollout_edge_function(EdgePlatform::Netlify, Some(&rollout), "", &redirects).unwrap();
+        assert_eq!(path, "mukti-rollout.js");
+        assert!(contents.contains("const PERCENTAGE = 10;"));
+
+        let table = contents
+            .split_once("const REDIRECTS = ")
+            .and_then(|(_, rest)| rest.split_once(";\n"))
+            .map(|(table, _)| table)
+            .unwrap();
+        let table: serde_json::Value = serde_json::from_str(table).unwrap();
+        assert_eq!(
+            table,
+            serde_json::json!({
+                "/latest/both": { "next": "next-both", "previous": "previous-both" },
+                "/latest/next-only": { "next": "next-only", "previous": "next-only" },
+                "/latest/previous-only": { "next": "previous-only", "previous": "previous-only" },
+            }),
+            "paths that only exist for one version always redirect to it"
+        );
+
+        let (path, contents) =
+            rollout_edge_function(EdgePlatform::Cloudflare, None, "/mukti", &redirects).unwrap();
+        assert_eq!(path, "mukti/latest/[[path]].js");
+        assert!(
+            contents.contains("const PERCENTAGE = 0;") && contents.contains("\"/latest/both\""),
+            "without a rollout, the function still redirects every path to the previous version"
+        );
+    }
+}
@@ -0,0 +1,115 @@
+// Copyright (c) The mukti Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Targets, formats and archive names, as specified on the command line.
+
+use crate::NameValueParseError;
+use std::str::FromStr;
+
+/// An archive uploaded for a release, parsed from `TARGET:FORMAT=NAME`.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct Archive {
+    /// The target and format of the archive.
+    pub target_format: TargetFormat,
+
+    /// The file name of the archive, relative to the archive prefix.
+    pub name: String,
+}
+
+impl Archive {
+    /// Creates a new archive.
+    pub fn new(target_format: TargetFormat, name: impl Into<String>) -> Self {
+        Self {
+            target_format,
+            name: name.into(),
+        }
+    }
+}
+
+impl FromStr for Archive {
+    type Err = NameValueParseError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let (target_format, name) = name_value_parse(input, '=')?;
+        let target_format: TargetFormat = target_format.parse()?;
+        Ok(Self {
+            target_format,
+            name,
+        })
+    }
+}
+
+/// An additional name to redirect to an archive, parsed from `ALIAS=TARGET:FORMAT`.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct Alias {
+    /// The path the alias is served at, relative to the version.
+    pub alias: String,
+
+    /// The target and format of the archive the alias redirects to.
+    pub target_format: TargetFormat,
+}
+
+impl Alias {
+    /// Creates a new alias.
+    pub fn new(alias: impl Into<String>, target_format: TargetFormat) -> Self {
+        Self {
+            alias: alias.into(),
+            target_format,
+        }
+    }
+}
+
+impl FromStr for Alias {
+    type Err = NameValueParseError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let (alias, target_format) = name_value_parse(input, '=')?;
+        let target_format: TargetFormat = target_format.parse()?;
+        Ok(Self {
+            alias,
+            target_format,
+        })
+    }
+}
+
+/// A target triple and archive format, parsed from `TARGET:FORMAT`.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct TargetFormat {
+    /// The target triple, e.g. `x86_64-unknown-linux-gnu`.
+    pub target: String,
+
+    /// The archive format, e.g. `tar.gz`.
+    pub format: String,
+}
+
+impl TargetFormat {
+    /// Creates a new target and format.
+    pub fn new(target: impl Into<String>, format: impl Into<String>) -> Self {
+        Self {
+            target: target.into(),
+            format: format.into(),
+        }
+    }
+}
+
+impl FromStr for TargetFormat {
+    type Err = NameValueParseError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let (target, format) = name_value_parse(input, ':')?;
+        Ok(Self { target, format })
+    }
+}
+
+fn name_value_parse(input: &str, delimiter: char) -> Result<(String, String), NameValueParseError> {
+    match input.split_once(delimiter) {
+        Some((k, v)) => Ok((k.to_owned(), v.to_owned())),
+        None => Err(NameValueParseError {
+            input: input.to_owned(),
+            delimiter,
+        }),
+    }
+}
@@ -0,0 +1,122 @@
+// Copyright (c) The mukti Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Generate per-version `targets.json` files.
+
+use crate::output::{public_url, single_project, write_output_file};
+use camino::Utf8Path;
+use eyre::Result;
+use mukti_metadata::{Digest, DigestAlgorithm, MuktiReleasesJson, ReleaseStatus};
+use semver::Version;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// The contents of a `<version>/targets.json` file.
+#[derive(Debug, Serialize)]
+struct TargetsJson<'a> {
+    version: &'a Version,
+    release_url: &'a str,
+    status: ReleaseStatus,
+    targets: Vec<TargetEntry<'a>>,
+}
+
+#[derive(Debug, Serialize)]
+struct TargetEntry<'a> {
+    target: &'a str,
+    format: &'a str,
+    /// The public URL, which redirects to `source_url`.
+    url: String,
+    /// The URL the artifact is hosted at.
+    source_url: &'a str,
+    checksums: &'a BTreeMap<DigestAlgorithm, Digest>,
+}
+
+/// Writes `<version>/targets.json` for every version, listing all of its artifacts.
+///
+/// The files sit alongside the redirects for each version, so consumers that need every artifact
+/// for a release can fetch one small file rather than the full release JSON.
+///
+/// Returns the number of versions written.
+pub fn generate_targets_json(
+    release_json: &MuktiReleasesJson,
+    base_url: &str,
+    out_dir: &Utf8Path,
+) -> Result<usize> {
+    let project = single_project(release_json)?;
+
+    let mut count = 0;
+    for (version, version_data) in project.all_versions() {
+        let targets_json = TargetsJson {
+            version,
+            release_url: &version_data.release_url,
+            status: version_data.status,
+            targets: version_data
+                .locations
+                .iter()
+                .map(|location| TargetEntry {
+                    target: &location.target,
+                    format: &location.format,
+                    url: public_url(base_url, version, &location.target, &location.format),
+                    source_url: &location.url,
+                    checksums: &location.checksums,
+                })
+                .collect(),
+        };
+
+        let mut out = serde_json::to_string_pretty(&targets_json)?;
+        out.push('\n');
+        write_output_file(out_dir, &format!("{}/targets.json", version), &out)?;
+        count += 1;
+    }
+
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::{fixture_json, version_data_mut};
+
+    #[test]
+    fn test_generate_targets_json() {
+        let mut json = fixture_json();
+        let checksums: BTreeMap<_, _> = [(DigestAlgorithm::SHA256, Digest("abcdef".to_owned()))]
+            .into_iter()
+            .collect();
+        version_data_mut(&mut json, &Version::new(0, 5, 3)).locations[0].checksums =
+            checksums.clone();
+
+        let dir = tempfile::tempdir().unwrap();
+        let out_dir = Utf8Path::from_path(dir.path()).unwrap();
+        let count = generate_targets_json(&json, "https://get.example.com", out_dir).unwrap();
+        assert_eq!(count, 4, "every version is written, including yanked ones");
+
+        let read = |version: &str| -> serde_json::Value {
+            let contents =
+                std::fs::read_to_string(out_dir.join(format!("{version}/targets.json"))).unwrap();
+            serde_json::from_str(&contents).unwrap()
+        };
+
+        let targets_json = read("0.5.3");
+        assert_eq!(targets_json["version"], "0.5.3");
+        assert_eq!(targets_json["status"], "active");
+        assert_eq!(
+            targets_json["targets"][0],
+            serde_json::json!({
+                "target": "x86_64-unknown-linux-gnu",
+                "format": "tar.gz",
+                "url": "https://get.example.com/0.5.3/x86_64-unknown-linux-gnu.tar.gz",
+                "source_url": "https://github.com/nextest-rs/mukti/releases/download/mukti-bin-0.5.3/mukti-bin-0.5.3-x86_64-unknown-linux-gnu.tar.gz",
+                "checksums": { "sha256": "abcdef" },
+            }),
+            "url is the public redirect, and source_url is where the artifact is hosted"
+        );
+        assert_eq!(targets_json["targets"].as_array().unwrap().len(), 3);
+
+        assert_eq!(
+            read("0.5.2")["status"],
+            "yanked",
+            "yanked versions are marked as such"
+        );
+    }
+}
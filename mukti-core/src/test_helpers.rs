@@ -0,0 +1,28 @@
+// Copyright (c) The mukti Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Helpers shared by tests.
+
+use mukti_metadata::{MuktiReleasesJson, ReleaseVersionData};
+use semver::Version;
+
+static FIXTURE_JSON: &str = include_str!("../../fixtures/mukti-releases.json");
+
+/// Returns the releases JSON fixture, which has a single `mukti` project.
+pub(crate) fn fixture_json() -> MuktiReleasesJson {
+    serde_json::from_str(FIXTURE_JSON).unwrap()
+}
+
+/// Returns the data for `version` in the `mukti` project, panicking if it doesn't exist.
+pub(crate) fn version_data_mut<'a>(
+    json: &'a mut MuktiReleasesJson,
+    version: &Version,
+) -> &'a mut ReleaseVersionData {
+    json.projects
+        .get_mut("mukti")
+        .unwrap()
+        .ranges
+        .values_mut()
+        .find_map(|range_data| range_data.versions.get_mut(version))
+        .unwrap_or_else(|| panic!("version {version} not found in fixture"))
+}
@@ -0,0 +1,433 @@
+// Copyright (c) The mukti Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! End-to-end verification of published artifacts.
+
+use crate::{
+    appcast::SparkleMetadata,
+    checksums::Checksums,
+    output::{public_url, resolve_version, single_project},
+};
+use base64::{prelude::BASE64_STANDARD, Engine as _};
+use ed25519_dalek::{Signature, Verifier as _, VerifyingKey};
+use eyre::{bail, eyre, Result, WrapErr};
+use futures_util::stream::StreamExt;
+use mukti_metadata::{MuktiReleasesJson, ReleaseLocation};
+use reqwest::{header, redirect::Policy, Client};
+use semver::Version;
+
+/// Options for artifact verification.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct VerifyOptions<'a> {
+    /// The base URL that redirects are served from, e.g. `https://get.nexte.st`.
+    pub base_url: &'a str,
+
+    /// The version to verify, or `None` for the latest version.
+    pub version: Option<&'a Version>,
+
+    /// The number of artifacts to download in parallel.
+    pub jobs: usize,
+
+    /// The base64-encoded EdDSA public key that signatures are checked against, as in Sparkle's
+    /// `SUPublicEDKey`. If `None`, signatures are not checked.
+    pub public_key: Option<&'a str>,
+}
+
+impl<'a> VerifyOptions<'a> {
+    /// Creates options for verifying the latest version, with 8 parallel downloads and no public
+    /// key.
+    pub fn new(base_url: &'a str) -> Self {
+        Self {
+            base_url,
+            version: None,
+            jobs: 8,
+            public_key: None,
+        }
+    }
+}
+
+/// The result of verifying the artifacts of a version.
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct VerifyReport {
+    /// The version that was verified.
+    pub version: Version,
+
+    /// One entry per artifact, in the order they appear in the release JSON.
+    pub artifacts: Vec<ArtifactReport>,
+}
+
+impl VerifyReport {
+    /// Returns the number of artifacts that failed verification.
+    pub fn failed_count(&self) -> usize {
+        self.artifacts
+            .iter()
+            .filter(|artifact| artifact.result.is_err())
+            .count()
+    }
+}
+
+/// The result of verifying a single artifact.
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct ArtifactReport {
+    /// The target triple of the artifact.
+    pub target: String,
+
+    /// The format of the artifact.
+    pub format: String,
+
+    /// The size recorded in the version's `sparkle` metadata, if any.
+    ///
+    /// If this is `Some` and verification succeeded, the downloaded size matched it.
+    pub recorded_size: Option<u64>,
+
+    /// Whether the signature recorded in the version's `sparkle` metadata was checked.
+    ///
+    /// If this is true and verification succeeded, the signature was valid.
+    pub signature_checked: bool,
+
+    /// The downloaded size on success, or the reason verification failed.
+    pub result: Result<u64>,
+}
+
+/// Downloads every artifact of a version through its public redirect URL and verifies it.
+///
+/// For each artifact, this checks that:
+///
+/// * the public URL redirects to the URL recorded in the release JSON,
+/// * the download succeeds,
+/// * its checksums match the ones recorded in the release JSON, and
+/// * if a size is recorded in the version's `sparkle` metadata (see
+///   [`generate_appcast`](crate::generate_appcast)), its size matches that, and
+/// * if a public key is provided and an EdDSA signature is recorded in the `sparkle` metadata,
+///   the signature is valid for the downloaded bytes.
+///
+/// Failures for individual artifacts are recorded in the report rather than returned as errors.
+pub async fn verify_artifacts(
+    release_json: &MuktiReleasesJson,
+    options: &VerifyOptions<'_>,
+) -> Result<VerifyReport> {
+    let project = single_project(release_json)?;
+    let (version, version_data) = resolve_version(project, options.version)?;
+    let public_key = options.public_key.map(parse_public_key).transpose()?;
+
+    // The public URL is fetched without following redirects so that the redirect target can be
+    // checked, and then the backing URL is fetched normally.
+    let redirect_client = Client::builder().redirect(Policy::none()).build()?;
+    let download_client = Client::new();
+
+    let tasks = version_data.locations.iter().map(|location| {
+        let url = public_url(
+            options.base_url,
+            version,
+            &location.target,
+            &location.format,
+        );
+        let sparkle = SparkleMetadata::from_version_data(
+            version_data,
+            &format!("{}.{}", location.target, location.format),
+        );
+        let recorded_size = sparkle.length;
+        let signature = public_key.as_ref().zip(sparkle.ed_signature);
+        let redirect_client = &redirect_client;
+        let download_client = &download_client;
+        async move {
+            let result = verify_location(
+                redirect_client,
+                download_client,
+                &url,
+                location,
+                recorded_size,
+                signature,
+            )
+            .await;
+            ArtifactReport {
+                target: location.target.clone(),
+                format: location.format.clone(),
+                recorded_size,
+                signature_checked: signature.is_some(),
+                result,
+            }
+        }
+    });
+    let artifacts = futures_util::stream::iter(tasks)
+        .buffered(options.jobs)
+        .collect()
+        .await;
+
+    Ok(VerifyReport {
+        version: version.clone(),
+        artifacts,
+    })
+}
+
+/// Parses a base64-encoded EdDSA public key.
+fn parse_public_key(public_key: &str) -> Result<VerifyingKey> {
+    let bytes = BASE64_STANDARD
+        .decode(public_key.trim())
+        .wrap_err("public key is not valid base64")?;
+    let bytes: [u8; 32] = bytes
+        .try_into()
+        .map_err(|bytes: Vec<u8>| eyre!("public key is {} bytes, expected 32", bytes.len()))?;
+    VerifyingKey::from_bytes(&bytes).wrap_err("public key is not a valid EdDSA key")
+}
+
+/// Verifies a single location, returning its size on success.
+///
+/// `signature` is the public key along with the base64-encoded signature recorded for the
+/// location, if it should be checked.
+async fn verify_location(
+    redirect_client: &Client,
+    download_client: &Client,
+    public_url: &str,
+    location: &ReleaseLocation,
+    recorded_size: Option<u64>,
+    signature: Option<(&VerifyingKey, &str)>,
+) -> Result<u64> {
+    let resp = redirect_client
+        .get(public_url)
+        .send()
+        .await
+        .wrap_err_with(|| format!("failed to fetch {}", public_url))?;
+    if !resp.status().is_redirection() {
+        bail!(
+            "expected {} to redirect, got status {}",
+            public_url,
+            resp.status()
+        );
+    }
+    let redirect_to = resp
+        .headers()
+        .get(header::LOCATION)
+        .and_then(|value| value.to_str().ok())
+        .ok_or_else(|| eyre!("redirect from {} has no valid Location header", public_url))?;
+    if redirect_to != location.url {
+        bail!(
+            "{} redirects to {}, expected {}",
+            public_url,
+            redirect_to,
+            location.url
+        );
+    }
+
+    let resp = download_client
+        .get(redirect_to)
+        .send()
+        .await
+        .and_then(|resp| resp.error_for_status())
+        .wrap_err_with(|| format!("failed to download {}", redirect_to))?;
+    let bytes = resp
+        .bytes()
+        .await
+        .wrap_err_with(|| format!("failed to download {}", redirect_to))?;
+    let size = bytes.len() as u64;
+    if let Some(recorded_size) = recorded_size {
+        if recorded_size != size {
+            bail!(
+                "downloaded {} bytes, but the recorded size is {}",
+                size,
+                recorded_size
+            );
+        }
+    }
+
+    if location.checksums.is_empty() {
+        bail!("no checksums recorded (run backfill-checksums first)");
+    }
+    let actual = Checksums::from_bytes(&bytes).to_checksum_map();
+    for (algorithm, expected) in &location.checksums {
+        // Algorithms mukti doesn't know how to compute are skipped.
+        if let Some(actual) = actual.get(algorithm) {
+            if !actual.0.eq_ignore_ascii_case(&expected.0) {
+                bail!(
+                    "{} checksum mismatch: expected {}, got {}",
+                    algorithm.as_str(),
+                    expected.0,
+                    actual.0
+                );
+            }
+        }
+    }
+
+    if let Some((public_key, signature)) = signature {
+        let signature = BASE64_STANDARD
+            .decode(signature)
+            .ok()
+            .and_then(|signature| Signature::from_slice(&signature).ok())
+            .ok_or_else(|| eyre!("recorded EdDSA signature is not valid base64-encoded Ed25519"))?;
+        if public_key.verify(&bytes, &signature).is_err() {
+            bail!("EdDSA signature does not match the public key");
+        }
+    }
+
+    Ok(size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::{Signer as _, SigningKey};
+    use std::{
+        io::{BufRead as _, BufReader, Write as _},
+        net::TcpListener,
+    };
+
+    static BODY: &[u8] = b"artifact contents";
+
+    /// Starts a server that serves `BODY` at `/artifact` and redirects every other path there.
+    ///
+    /// Returns the base URL of the server, which runs until the test process exits.
+    fn serve() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let artifact_url = format!("{}/artifact", base_url);
+
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(&stream);
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                // Skip headers.
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap() > 2 {
+                    line.clear();
+                }
+
+                let path = request_line.split(' ').nth(1).unwrap_or_default();
+                if path == "/artifact" {
+                    write!(
+                        stream,
+                        "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                        BODY.len()
+                    )
+                    .unwrap();
+                    stream.write_all(BODY).unwrap();
+                } else {
+                    write!(
+                        stream,
+                        "HTTP/1.1 302 Found\r\nLocation: {}\r\nContent-Length: 0\r\n\
+                         Connection: close\r\n\r\n",
+                        artifact_url
+                    )
+                    .unwrap();
+                }
+            }
+        });
+
+        base_url
+    }
+
+    fn location(url: String, contents: &[u8]) -> ReleaseLocation {
+        ReleaseLocation {
+            target: "x86_64-unknown-linux-gnu".to_owned(),
+            format: "tar.gz".to_owned(),
+            url,
+            checksums: Checksums::from_bytes(contents).to_checksum_map(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_verify_location() {
+        let base_url = serve();
+        let public_url = format!("{}/0.5.3/x86_64-unknown-linux-gnu.tar.gz", base_url);
+        let artifact_url = format!("{}/artifact", base_url);
+        let redirect_client = Client::builder()
+            .redirect(Policy::none())
+            .no_proxy()
+            .build()
+            .unwrap();
+        let download_client = Client::builder().no_proxy().build().unwrap();
+
+        let signing_key = SigningKey::from_bytes(&[7; 32]);
+        let public_key =
+            parse_public_key(&BASE64_STANDARD.encode(signing_key.verifying_key().as_bytes()))
+                .unwrap();
+        let signature = BASE64_STANDARD.encode(signing_key.sign(BODY).to_bytes());
+        let wrong_signature = BASE64_STANDARD.encode(signing_key.sign(b"other").to_bytes());
+
+        let size = BODY.len() as u64;
+        let verify = |location: ReleaseLocation, recorded_size, signature| {
+            let (redirect_client, download_client, public_url) =
+                (&redirect_client, &download_client, &public_url);
+            async move {
+                verify_location(
+                    redirect_client,
+                    download_client,
+                    public_url,
+                    &location,
+                    recorded_size,
+                    signature,
+                )
+                .await
+            }
+        };
+
+        assert_eq!(
+            verify(
+                location(artifact_url.clone(), BODY),
+                Some(size),
+                Some((&public_key, signature.as_str())),
+            )
+            .await
+            .unwrap(),
+            size,
+        );
+
+        for (location, recorded_size, signature, expected, reason) in [
+            (
+                location(format!("{}/elsewhere", base_url), BODY),
+                None,
+                None,
+                "redirects to",
+                "wrong Location",
+            ),
+            (
+                location(artifact_url.clone(), b"other"),
+                None,
+                None,
+                "checksum mismatch",
+                "checksum mismatch",
+            ),
+            (
+                location(artifact_url.clone(), BODY),
+                Some(size + 1),
+                None,
+                "recorded size",
+                "size mismatch",
+            ),
+            (
+                location(artifact_url.clone(), BODY),
+                Some(size),
+                Some((&public_key, wrong_signature.as_str())),
+                "signature does not match",
+                "signature mismatch",
+            ),
+        ] {
+            let err = verify(location, recorded_size, signature)
+                .await
+                .expect_err(reason);
+            assert!(
+                err.to_string().contains(expected),
+                "{reason}: unexpected error {err}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_public_key() {
+        let signing_key = SigningKey::from_bytes(&[7; 32]);
+        let encoded = BASE64_STANDARD.encode(signing_key.verifying_key().as_bytes());
+        assert_eq!(
+            parse_public_key(&encoded).unwrap(),
+            signing_key.verifying_key()
+        );
+        assert!(parse_public_key("not base64!").is_err());
+        assert!(
+            parse_public_key(&BASE64_STANDARD.encode([0; 16])).is_err(),
+            "wrong length"
+        );
+    }
+}
@@ -0,0 +1,58 @@
+// Copyright (c) The mukti Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Generate plain-text version lists.
+
+use crate::output::{active_versions_ascending, single_project, write_output_file};
+use camino::Utf8Path;
+use eyre::Result;
+use mukti_metadata::MuktiReleasesJson;
+use std::fmt::Write as _;
+
+/// Writes `versions/all` and `versions/stable`, newline-separated lists of active versions in
+/// ascending order.
+///
+/// `versions/all` includes pre-releases, while `versions/stable` doesn't. Pass `versions: true`
+/// to [`generate_redirects`](crate::generate_redirects) (`--versions` on the command line) to
+/// also serve the full list at `/versions`.
+pub fn generate_versions(release_json: &MuktiReleasesJson, out_dir: &Utf8Path) -> Result<()> {
+    let project = single_project(release_json)?;
+
+    let mut all = String::with_capacity(4096);
+    let mut stable = String::with_capacity(4096);
+    for (version, _) in active_versions_ascending(project) {
+        writeln!(all, "{}", version)?;
+        if version.pre.is_empty() {
+            writeln!(stable, "{}", version)?;
+        }
+    }
+
+    write_output_file(out_dir, "versions/all", &all)?;
+    write_output_file(out_dir, "versions/stable", &stable)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::fixture_json;
+
+    #[test]
+    fn test_generate_versions() {
+        let dir = tempfile::tempdir().unwrap();
+        let out_dir = Utf8Path::from_path(dir.path()).unwrap();
+        generate_versions(&fixture_json(), out_dir).unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(out_dir.join("versions/all")).unwrap(),
+            "0.5.1\n0.5.3\n0.6.0-alpha.1\n",
+            "yanked 0.5.2 is left out, and pre-releases are included"
+        );
+        assert_eq!(
+            std::fs::read_to_string(out_dir.join("versions/stable")).unwrap(),
+            "0.5.1\n0.5.3\n",
+            "pre-releases are left out"
+        );
+    }
+}
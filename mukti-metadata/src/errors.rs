@@ -1,10 +1,111 @@
 // Copyright (c) The mukti Contributors
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
-use crate::VersionRangeKind;
-use std::num::ParseIntError;
+use crate::{VersionRange, VersionRangeKind};
+use semver::Version;
+use std::{num::ParseIntError, path::PathBuf};
 use thiserror::Error;
 
+/// An error that occurred while reading or validating release metadata.
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum MetadataError {
+    /// An I/O error occurred while reading release metadata.
+    #[error("error reading release metadata from `{}`", .path.display())]
+    Io {
+        /// The path that was being read.
+        path: PathBuf,
+
+        /// The error that occurred.
+        #[source]
+        error: std::io::Error,
+    },
+
+    /// The release metadata could not be deserialized.
+    #[error("error parsing release metadata at `{json_path}`")]
+    Parse {
+        /// The path within the JSON document where the error occurred, e.g.
+        /// `projects.mukti.ranges.0.5.latest`.
+        json_path: String,
+
+        /// The error that occurred.
+        #[source]
+        error: serde_json::Error,
+    },
+
+    /// A version is stored under a range it doesn't belong to.
+    #[error("in project `{project}`, version {version} is stored under range {range}, but belongs to range {expected}")]
+    VersionRangeMismatch {
+        /// The name of the project.
+        project: String,
+
+        /// The range the version is stored under.
+        range: VersionRange,
+
+        /// The offending version.
+        version: Version,
+
+        /// The range the version belongs to.
+        expected: VersionRange,
+    },
+
+    /// The latest version of a range is missing from the range.
+    #[error("in project `{project}`, latest version {version} of range {range} was not found")]
+    MissingRangeLatest {
+        /// The name of the project.
+        project: String,
+
+        /// The offending range.
+        range: VersionRange,
+
+        /// The latest version recorded for the range.
+        version: Version,
+    },
+
+    /// The `is_prerelease` flag of a range doesn't match its versions.
+    #[error("in project `{project}`, range {range} has is_prerelease set to {is_prerelease}, which doesn't match its versions")]
+    PrereleaseMismatch {
+        /// The name of the project.
+        project: String,
+
+        /// The offending range.
+        range: VersionRange,
+
+        /// The recorded value of `is_prerelease`.
+        is_prerelease: bool,
+    },
+
+    /// The latest range of a project is missing or only contains pre-releases.
+    #[error("in project `{project}`, latest range {range} was not found or only has pre-releases")]
+    InvalidLatestRange {
+        /// The name of the project.
+        project: String,
+
+        /// The offending range.
+        range: VersionRange,
+    },
+
+    /// A version referred to by a rollout was not found.
+    #[error("in project `{project}`, rollout refers to version {version}, which was not found")]
+    MissingRolloutVersion {
+        /// The name of the project.
+        project: String,
+
+        /// The offending version.
+        version: Version,
+    },
+
+    /// A rollout percentage is out of range.
+    #[error("in project `{project}`, rollout percentage {percentage} is greater than 100")]
+    InvalidRolloutPercentage {
+        /// The name of the project.
+        project: String,
+
+        /// The offending percentage.
+        percentage: u8,
+    },
+}
+
 #[derive(Debug, Error)]
 #[non_exhaustive]
 #[error("error parsing version range `{input}` at {} component", .component.description())]
@@ -0,0 +1,104 @@
+// Copyright (c) The mukti Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use semver::Version;
+use serde::{Deserialize, Serialize};
+
+/// An entry in the release event log.
+///
+/// The event log is a newline-delimited JSON file, with one `ReleaseEvent` per line in the order
+/// the events happened.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ReleaseEvent {
+    /// When the event happened, as an RFC 3339 timestamp.
+    pub timestamp: String,
+
+    /// The user or automation that caused the event, if known.
+    pub actor: Option<String>,
+
+    /// The project the event applies to.
+    pub project: String,
+
+    /// What happened.
+    #[serde(flatten)]
+    pub kind: ReleaseEventKind,
+}
+
+/// The kind of a [`ReleaseEvent`].
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(tag = "event", rename_all = "kebab-case")]
+#[non_exhaustive]
+pub enum ReleaseEventKind {
+    /// A release was added, or an existing release was replaced.
+    ReleaseAdded {
+        /// The version that was added.
+        version: Version,
+
+        /// Canonical URL for the release.
+        release_url: String,
+
+        /// The URLs of the release locations.
+        location_urls: Vec<String>,
+    },
+
+    /// Checksums were added to existing release locations.
+    ChecksumsBackfilled {
+        /// The URLs of the locations whose checksums were updated.
+        location_urls: Vec<String>,
+    },
+
+    /// A staged rollout of a version was started.
+    RolloutStarted {
+        /// The version being rolled out.
+        version: Version,
+
+        /// The version served to requests that aren't part of the rollout.
+        previous: Version,
+
+        /// The initial rollout percentage.
+        percentage: u8,
+    },
+
+    /// The percentage of a staged rollout was changed. A percentage of 100 completes the rollout.
+    RolloutAdvanced {
+        /// The version being rolled out.
+        version: Version,
+
+        /// The new rollout percentage.
+        percentage: u8,
+    },
+
+    /// A staged rollout was aborted.
+    RolloutAborted {
+        /// The version that was being rolled out.
+        version: Version,
+    },
+
+    /// An aborted or superseded staged rollout was removed.
+    RolloutCleared {
+        /// The version that was being rolled out.
+        version: Version,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_format() {
+        let event = ReleaseEvent {
+            timestamp: "2024-11-24T00:00:00Z".to_owned(),
+            actor: Some("octocat".to_owned()),
+            project: "mukti".to_owned(),
+            kind: ReleaseEventKind::ChecksumsBackfilled {
+                location_urls: vec!["https://example.com/a.tar.gz".to_owned()],
+            },
+        };
+
+        assert_eq!(
+            serde_json::to_string(&event).unwrap(),
+            r#"{"timestamp":"2024-11-24T00:00:00Z","actor":"octocat","project":"mukti","event":"checksums-backfilled","location_urls":["https://example.com/a.tar.gz"]}"#,
+        );
+    }
+}
@@ -2,7 +2,9 @@
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
 mod errors;
+mod events;
 mod models;
 
 pub use errors::*;
+pub use events::*;
 pub use models::*;
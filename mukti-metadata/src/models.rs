@@ -1,10 +1,10 @@
 // Copyright (c) The mukti Contributors
 // SPDX-License-Identifier: MIT or Apache-2.0
 
-use crate::VersionRangeParseError;
+use crate::{MetadataError, VersionRangeParseError};
 use semver::{Version, VersionReq};
 use serde::{de::Visitor, ser::SerializeMap, Deserialize, Serialize, Serializer};
-use std::{borrow::Cow, collections::BTreeMap, fmt, str::FromStr};
+use std::{borrow::Cow, collections::BTreeMap, fmt, path::Path, str::FromStr};
 
 #[derive(Clone, Debug, Default, Deserialize, Serialize)]
 pub struct MuktiReleasesJson {
@@ -12,6 +12,43 @@ pub struct MuktiReleasesJson {
     pub projects: BTreeMap<String, MuktiProject>,
 }
 
+impl MuktiReleasesJson {
+    /// Parses release metadata from a JSON string.
+    ///
+    /// This doesn't check that the metadata is internally consistent: call
+    /// [`validate`](Self::validate) for that.
+    pub fn from_json_str(json: &str) -> Result<Self, MetadataError> {
+        let deserializer = &mut serde_json::Deserializer::from_str(json);
+        let release_json: Self =
+            serde_path_to_error::deserialize(deserializer).map_err(|err| MetadataError::Parse {
+                json_path: err.path().to_string(),
+                error: err.into_inner(),
+            })?;
+        Ok(release_json)
+    }
+
+    /// Reads release metadata from a file.
+    ///
+    /// As with [`from_json_str`](Self::from_json_str), this doesn't check that the metadata is
+    /// internally consistent.
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self, MetadataError> {
+        let path = path.as_ref();
+        let json = std::fs::read_to_string(path).map_err(|error| MetadataError::Io {
+            path: path.to_owned(),
+            error,
+        })?;
+        Self::from_json_str(&json)
+    }
+
+    /// Checks that the release metadata is internally consistent.
+    pub fn validate(&self) -> Result<(), MetadataError> {
+        for (name, project) in &self.projects {
+            project.validate(name)?;
+        }
+        Ok(())
+    }
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct MuktiProject {
     /// The latest version range (key in the releases field) without any pre-releases.
@@ -20,6 +57,10 @@ pub struct MuktiProject {
     /// Map of version range (major or minor version) to release data about it
     #[serde(serialize_with = "serialize_reverse")]
     pub ranges: BTreeMap<VersionRange, ReleaseRangeData>,
+
+    /// A staged rollout of the latest version, if one is in progress.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rollout: Option<Rollout>,
 }
 
 impl MuktiProject {
@@ -42,6 +83,71 @@ impl MuktiProject {
             .find(|&(v2, _)| eq_ignoring_build_metadata(version, v2))
     }
 
+    fn validate(&self, name: &str) -> Result<(), MetadataError> {
+        for (range, data) in &self.ranges {
+            for version in data.versions.keys() {
+                let expected = VersionRange::from_version(version);
+                if &expected != range {
+                    return Err(MetadataError::VersionRangeMismatch {
+                        project: name.to_owned(),
+                        range: *range,
+                        version: version.clone(),
+                        expected,
+                    });
+                }
+            }
+
+            if !data.versions.contains_key(&data.latest) {
+                return Err(MetadataError::MissingRangeLatest {
+                    project: name.to_owned(),
+                    range: *range,
+                    version: data.latest.clone(),
+                });
+            }
+
+            let only_prereleases = data.versions.keys().all(|version| !version.pre.is_empty());
+            if data.is_prerelease != only_prereleases {
+                return Err(MetadataError::PrereleaseMismatch {
+                    project: name.to_owned(),
+                    range: *range,
+                    is_prerelease: data.is_prerelease,
+                });
+            }
+        }
+
+        if let Some(range) = &self.latest {
+            if self
+                .ranges
+                .get(range)
+                .map_or(true, |data| data.is_prerelease)
+            {
+                return Err(MetadataError::InvalidLatestRange {
+                    project: name.to_owned(),
+                    range: *range,
+                });
+            }
+        }
+
+        if let Some(rollout) = &self.rollout {
+            for version in [&rollout.version, &rollout.previous] {
+                if self.get_version_data(version).is_none() {
+                    return Err(MetadataError::MissingRolloutVersion {
+                        project: name.to_owned(),
+                        version: version.clone(),
+                    });
+                }
+            }
+            if rollout.percentage > 100 {
+                return Err(MetadataError::InvalidRolloutPercentage {
+                    project: name.to_owned(),
+                    percentage: rollout.percentage,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
     /// Retrieve the latest version that matches this `VersionReq`.
     ///
     /// This will match the latest non-pre-release, non-yanked version.
@@ -52,6 +158,20 @@ impl MuktiProject {
     }
 }
 
+/// A staged rollout of a new version as the latest version.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Rollout {
+    /// The version being rolled out.
+    pub version: Version,
+
+    /// The version served to requests that aren't part of the rollout.
+    pub previous: Version,
+
+    /// The percentage of requests for the latest version that are served `version`, from 0 to
+    /// 100. A percentage of 0 means the rollout was aborted.
+    pub percentage: u8,
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct ReleaseRangeData {
     /// The latest version within this range (can be a prerelease)
@@ -127,6 +247,11 @@ impl DigestAlgorithm {
     pub fn new(algorithm: String) -> Self {
         Self(Cow::Owned(algorithm))
     }
+
+    /// Returns the name of this algorithm.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
 }
 
 /// A digest, typically encoded as a hex string.
@@ -265,6 +390,90 @@ mod tests {
 
     static FIXTURE_JSON: &str = include_str!("../../fixtures/mukti-releases.json");
 
+    fn validate_modified(modify: impl FnOnce(&mut serde_json::Value)) -> Result<(), MetadataError> {
+        let mut json: serde_json::Value = serde_json::from_str(FIXTURE_JSON).unwrap();
+        modify(&mut json);
+        MuktiReleasesJson::from_json_str(&json.to_string())
+            .expect("modified fixture parses")
+            .validate()
+    }
+
+    #[test]
+    fn test_validate() {
+        MuktiReleasesJson::from_json_str(FIXTURE_JSON)
+            .unwrap()
+            .validate()
+            .expect("fixture is valid");
+
+        match validate_modified(|json| {
+            json["projects"]["mukti"]["ranges"]["0.5"]["latest"] = "0.5.9".into();
+        }) {
+            Err(MetadataError::MissingRangeLatest {
+                project,
+                range,
+                version: v,
+            }) => {
+                assert_eq!(project, "mukti");
+                assert_eq!(range, VersionRange::Minor(5));
+                assert_eq!(v, version("0.5.9"));
+            }
+            other => panic!("expected MissingRangeLatest, found {other:?}"),
+        }
+
+        match validate_modified(|json| {
+            let ranges = &mut json["projects"]["mukti"]["ranges"];
+            ranges["0.6"]["versions"]["0.5.1"] = ranges["0.5"]["versions"]["0.5.1"].clone();
+        }) {
+            Err(MetadataError::VersionRangeMismatch {
+                range,
+                version: v,
+                expected,
+                ..
+            }) => {
+                assert_eq!(range, VersionRange::Minor(6));
+                assert_eq!(v, version("0.5.1"));
+                assert_eq!(expected, VersionRange::Minor(5));
+            }
+            other => panic!("expected VersionRangeMismatch, found {other:?}"),
+        }
+
+        match validate_modified(|json| {
+            json["projects"]["mukti"]["ranges"]["0.6"]["is_prerelease"] = false.into();
+        }) {
+            Err(MetadataError::PrereleaseMismatch {
+                range,
+                is_prerelease,
+                ..
+            }) => {
+                assert_eq!(range, VersionRange::Minor(6));
+                assert!(!is_prerelease);
+            }
+            other => panic!("expected PrereleaseMismatch, found {other:?}"),
+        }
+
+        match validate_modified(|json| {
+            json["projects"]["mukti"]["rollout"] = serde_json::json!({
+                "version": "0.5.3",
+                "previous": "0.5.1",
+                "percentage": 101,
+            });
+        }) {
+            Err(MetadataError::InvalidRolloutPercentage { percentage, .. }) => {
+                assert_eq!(percentage, 101);
+            }
+            other => panic!("expected InvalidRolloutPercentage, found {other:?}"),
+        }
+
+        let mut json: serde_json::Value = serde_json::from_str(FIXTURE_JSON).unwrap();
+        json["projects"]["mukti"]["ranges"]["0.5"]["is_prerelease"] = "no".into();
+        match MuktiReleasesJson::from_json_str(&json.to_string()) {
+            Err(MetadataError::Parse { json_path, .. }) => {
+                assert_eq!(json_path, "projects.mukti.ranges.0.5.is_prerelease");
+            }
+            other => panic!("expected Parse, found {other:?}"),
+        }
+    }
+
     #[test]
     fn test_get_version_data() {
         let json: MuktiReleasesJson = serde_json::from_str(FIXTURE_JSON).unwrap();